@@ -0,0 +1,218 @@
+#[macro_use] extern crate log;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use chrono::Local;
+use fern::Dispatch;
+use log::LevelFilter;
+use clap::{App, Arg, ArgGroup, ArgMatches};
+use tantivy::Index;
+use tantivy::directory::MmapDirectory;
+use winvanilla::diff::{diff_file_lists, diff_index_versions, BuildDiff};
+use winvanilla::index::WindowsRefIndexReader;
+use winvanilla::triage::ReportFormat;
+use winvanilla::vanilla::WindowsFileList;
+
+#[cfg(windows)]
+#[global_allocator]
+static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
+
+static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Create and return an App that is used to parse the command line params
+/// that were specified by the user.
+///
+fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
+    let old_folder_arg = Arg::with_name("old_folder")
+        .long("old-folder")
+        .value_name("OLD_FOLDER")
+        .takes_value(true)
+        .requires("new_folder")
+        .help("The data set folder of the old build.");
+
+    let new_folder_arg = Arg::with_name("new_folder")
+        .long("new-folder")
+        .value_name("NEW_FOLDER")
+        .takes_value(true)
+        .requires("old_folder")
+        .help("The data set folder of the new build.");
+
+    let index_arg = Arg::with_name("index_location")
+        .short("-i")
+        .long("index-location")
+        .value_name("INDEX_LOCATION")
+        .takes_value(true)
+        .requires_all(&["old_version", "new_version"])
+        .help("The index folder to compare data sets of.");
+
+    let old_version_arg = Arg::with_name("old_version")
+        .long("old-version")
+        .value_name("OLD_VERSION")
+        .takes_value(true)
+        .requires("index_location")
+        .help("The DataSetPath or OS version of the old build (e.g. 'Windows10/1909/W10_1909_Pro_18363.418' or '10.0.18363 N/A Build 18363'). An OS version must be in a single data set.");
+
+    let new_version_arg = Arg::with_name("new_version")
+        .long("new-version")
+        .value_name("NEW_VERSION")
+        .takes_value(true)
+        .requires("index_location")
+        .help("The DataSetPath or OS version of the new build (e.g. 'Windows10/20H2/W10_20H2_Pro_19042.631' or '10.0.19042 N/A Build 19042'). An OS version must be in a single data set.");
+
+    let output_arg = Arg::with_name("output")
+        .short("-o")
+        .long("output")
+        .required(false)
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .help("The report file (otherwise the report is written to stdout).");
+
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .default_value("jsonl")
+        .possible_values(&["jsonl", "csv"])
+        .help("The report format.");
+
+    let logging_arg = Arg::with_name("logging")
+        .long("logging")
+        .value_name("LOGGING LEVEL")
+        .takes_value(true)
+        .default_value("Info")
+        .possible_values(&["Off", "Error", "Warn", "Info", "Debug", "Trace"])
+        .help("Logging level to use.");
+
+    App::new("vanilla_diff")
+        .version(VERSION)
+        .author("Matthew Seyer <https://github.com/forensicmatt/VanillaWindowsTools>")
+        .about("Report the files added, removed and modified between two Windows builds.")
+        .arg(old_folder_arg)
+        .arg(new_folder_arg)
+        .arg(index_arg)
+        .arg(old_version_arg)
+        .arg(new_version_arg)
+        .group(
+            ArgGroup::with_name("source")
+                .args(&["old_folder", "index_location"])
+                .required(true)
+        )
+        .arg(output_arg)
+        .arg(format_arg)
+        .arg(logging_arg)
+}
+
+
+/// Set the logging level from the CLI parsed parameters.
+///
+fn set_logging_level(matches: &ArgMatches){
+    // Get the logging level supplied by the user
+    let message_level = match matches.value_of("logging") {
+        Some("Off") => LevelFilter::Off,
+        Some("Error") => LevelFilter::Error,
+        Some("Warn") => LevelFilter::Warn,
+        Some("Info") => LevelFilter::Info,
+        Some("Debug") => LevelFilter::Debug,
+        Some("Trace") => LevelFilter::Trace,
+        Some(unknown) => {
+            eprintln!("Unknown log level [{}]", unknown);
+            exit(-1);
+        },
+        None => {
+            LevelFilter::Off
+        }
+    };
+
+    // Create logging with debug level that prints to stderr
+    // See https://docs.rs/fern/0.6.0/fern/#example-setup
+    let result = Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                Local::now().format("[%Y-%m-%d %H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message
+            ))
+        })
+        .level(message_level)
+        .chain(std::io::stderr())
+        .apply();
+
+    // Ensure that logger was dispatched
+    match result {
+        Ok(_) => trace!("Logging as been initialized!"),
+        Err(error) => {
+            eprintln!("Error initializing fern logging: {}", error);
+            exit(-1);
+        }
+    }
+}
+
+
+/// Diff the two data set folders or the two data sets of the index.
+///
+fn get_diff(matches: &ArgMatches) -> Result<BuildDiff, String> {
+    if let Some(index_location) = matches.value_of("index_location") {
+        let old_version = matches.value_of("old_version")
+            .expect("No old_version provided.");
+        let new_version = matches.value_of("new_version")
+            .expect("No new_version provided.");
+
+        let index_directory = MmapDirectory::open(index_location)
+            .map_err(|e| format!("Error opening index_location: {:?}", e))?;
+        let index = Index::open(index_directory)
+            .map_err(|e| format!("Error opening index: {:?}", e))?;
+        let index_reader = WindowsRefIndexReader::try_from(index)
+            .map_err(|e| format!("Error creating WindowsRefIndexReader: {:?}", e))?;
+
+        return diff_index_versions(&index_reader, old_version, new_version)
+            .map_err(|e| e.to_string());
+    }
+
+    let old = WindowsFileList::from_folder(
+        matches.value_of("old_folder").expect("No old_folder provided.")
+    )?;
+    let new = WindowsFileList::from_folder(
+        matches.value_of("new_folder").expect("No new_folder provided.")
+    )?;
+    diff_file_lists(&old, &new)
+}
+
+
+/// The main entry point for this tool.
+///
+fn main() {
+    let arg_parser = get_argument_parser();
+    let options = arg_parser.get_matches();
+
+    set_logging_level(&options);
+
+    let format: ReportFormat = options.value_of("format")
+        .expect("No format provided.")
+        .parse()
+        .expect("Unable to parse format.");
+
+    let diff = match get_diff(&options) {
+        Ok(d) => d,
+        Err(e) => {
+            eprintln!("{}", e);
+            exit(-1);
+        }
+    };
+
+    let output: Box<dyn Write> = match options.value_of("output") {
+        Some(path) => Box::new(
+            File::create(path).expect("Error creating output file.")
+        ),
+        None => Box::new(std::io::stdout())
+    };
+    diff.write_changes(BufWriter::new(output), format)
+        .expect("Error writing report.");
+
+    info!(
+        "Added: {}, Removed: {}, Modified: {}, Unchanged: {}",
+        diff.added, diff.removed, diff.modified, diff.unchanged
+    );
+}
@@ -0,0 +1,171 @@
+#[macro_use] extern crate log;
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::exit;
+use chrono::Local;
+use fern::Dispatch;
+use log::LevelFilter;
+use clap::{App, Arg, ArgMatches};
+use winvanilla::export::ExportStats;
+use winvanilla::export::hashlist::export_hash_list;
+use winvanilla::export::nsrl::{export_rds, LegacyRdsWriter, Rds3Writer};
+
+#[cfg(windows)]
+#[global_allocator]
+static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
+
+static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Create and return an App that is used to parse the command line params
+/// that were specified by the user.
+///
+fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
+    let source_arg = Arg::with_name("source")
+        .short("-s")
+        .long("source")
+        .required(true)
+        .value_name("SOURCE")
+        .takes_value(true)
+        .help("The source folder");
+
+    let output_arg = Arg::with_name("output")
+        .short("-o")
+        .long("output")
+        .required(true)
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .help("The output (a folder for nsrl-legacy, otherwise a file).");
+
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .required(true)
+        .possible_values(&["nsrl-legacy", "nsrl-rds3", "hashdeep", "md5sum", "sha256sum", "hfind-md5"])
+        .help("The export format. The SHA-1 and CRC32 columns of the nsrl formats are empty, since the reference only has MD5 and SHA256.");
+
+    let os_version_arg = Arg::with_name("os_version")
+        .long("os-version")
+        .value_name("OS_VERSION")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Only export data sets of this OS version (e.g. '10.0.19042'). Can be used multiple times.");
+
+    let logging_arg = Arg::with_name("logging")
+        .long("logging")
+        .value_name("LOGGING LEVEL")
+        .takes_value(true)
+        .default_value("Info")
+        .possible_values(&["Off", "Error", "Warn", "Info", "Debug", "Trace"])
+        .help("Logging level to use.");
+
+    App::new("vanilla_export")
+        .version(VERSION)
+        .author("Matthew Seyer <https://github.com/forensicmatt/VanillaWindowsTools>")
+        .about("Export VanillaWindowsReference files as known-good hash sets.")
+        .arg(source_arg)
+        .arg(output_arg)
+        .arg(format_arg)
+        .arg(os_version_arg)
+        .arg(logging_arg)
+}
+
+
+/// Set the logging level from the CLI parsed parameters.
+///
+fn set_logging_level(matches: &ArgMatches){
+    // Get the logging level supplied by the user
+    let message_level = match matches.value_of("logging") {
+        Some("Off") => LevelFilter::Off,
+        Some("Error") => LevelFilter::Error,
+        Some("Warn") => LevelFilter::Warn,
+        Some("Info") => LevelFilter::Info,
+        Some("Debug") => LevelFilter::Debug,
+        Some("Trace") => LevelFilter::Trace,
+        Some(unknown) => {
+            eprintln!("Unknown log level [{}]", unknown);
+            exit(-1);
+        },
+        None => {
+            LevelFilter::Off
+        }
+    };
+
+    // Create logging with debug level that prints to stderr
+    // See https://docs.rs/fern/0.6.0/fern/#example-setup
+    let result = Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                Local::now().format("[%Y-%m-%d %H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message
+            ))
+        })
+        .level(message_level)
+        .chain(std::io::stderr())
+        .apply();
+
+    // Ensure that logger was dispatched
+    match result {
+        Ok(_) => trace!("Logging as been initialized!"),
+        Err(error) => {
+            eprintln!("Error initializing fern logging: {}", error);
+            exit(-1);
+        }
+    }
+}
+
+
+/// Export the source in the format from the CLI parsed parameters.
+///
+fn export(matches: &ArgMatches) -> Result<ExportStats, String> {
+    let source = matches.value_of("source")
+        .expect("No source folder was provided.");
+    let output = matches.value_of("output")
+        .expect("No output was provided.");
+
+    let os_versions: Vec<String> = matches.values_of("os_version")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
+
+    if matches.value_of("format").is_some_and(|f| f.starts_with("nsrl")) {
+        warn!("The reference has no SHA-1 or CRC32 values, so those columns are empty. Tools that key on SHA-1 will not match the export.");
+    }
+
+    match matches.value_of("format") {
+        Some("nsrl-legacy") => export_rds(source, &os_versions, &mut LegacyRdsWriter::create(output)?),
+        Some("nsrl-rds3") => export_rds(source, &os_versions, &mut Rds3Writer::create(output)?),
+        Some(format) => {
+            let format = format.parse()?;
+            let fh = File::create(output)
+                .map_err(|e| format!("Could not create '{}'. {:?}", output, e))?;
+            export_hash_list(source, &os_versions, format, BufWriter::new(fh))
+        },
+        None => Err("No format was provided.".to_string())
+    }
+}
+
+
+/// The main entry point for this tool.
+///
+fn main() {
+    let arg_parser = get_argument_parser();
+    let options = arg_parser.get_matches();
+
+    set_logging_level(&options);
+
+    match export(&options) {
+        Ok(stats) => info!(
+            "Exported {} files from {} data sets ({} duplicates, {} skipped without hashes)",
+            stats.files, stats.data_sets, stats.duplicates, stats.skipped
+        ),
+        Err(e) => {
+            eprintln!("Error exporting: {}", e);
+            exit(-1);
+        }
+    }
+}
@@ -0,0 +1,197 @@
+#[macro_use] extern crate log;
+use std::process::exit;
+use chrono::Local;
+use fern::Dispatch;
+use log::LevelFilter;
+use clap::{App, Arg, ArgMatches, SubCommand};
+use winvanilla::filter::{HashFilter, HashFilterBuilder};
+
+#[cfg(windows)]
+#[global_allocator]
+static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
+
+static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Create and return an App that is used to parse the command line params
+/// that were specified by the user.
+///
+fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
+    let source_arg = Arg::with_name("source")
+        .short("-s")
+        .long("source")
+        .required(true)
+        .value_name("SOURCE")
+        .takes_value(true)
+        .help("The source folder");
+
+    let output_arg = Arg::with_name("output")
+        .short("-o")
+        .long("output")
+        .required(true)
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .help("The filter file to create.");
+
+    let os_version_arg = Arg::with_name("os_version")
+        .long("os-version")
+        .value_name("OS_VERSION")
+        .takes_value(true)
+        .multiple(true)
+        .number_of_values(1)
+        .help("Only add data sets of this OS version (e.g. '10.0.19042'). Can be used multiple times.");
+
+    let filter_arg = Arg::with_name("filter")
+        .short("-f")
+        .long("filter")
+        .required(true)
+        .value_name("FILTER")
+        .takes_value(true)
+        .help("The filter file.");
+
+    let hash_arg = Arg::with_name("hashes")
+        .value_name("HASH")
+        .multiple(true)
+        .required(true)
+        .help("MD5 or SHA256 hashes to check.");
+
+    let logging_arg = Arg::with_name("logging")
+        .long("logging")
+        .value_name("LOGGING LEVEL")
+        .takes_value(true)
+        .default_value("Info")
+        .possible_values(&["Off", "Error", "Warn", "Info", "Debug", "Trace"])
+        .help("Logging level to use.");
+
+    App::new("vanilla_filter")
+        .version(VERSION)
+        .author("Matthew Seyer <https://github.com/forensicmatt/VanillaWindowsTools>")
+        .about("Build and check compact hash filters of VanillaWindowsReference files.")
+        .arg(logging_arg)
+        .subcommand(
+            SubCommand::with_name("build")
+                .about("Build a filter from the MD5 and SHA256 hashes of the reference.")
+                .arg(source_arg)
+                .arg(output_arg)
+                .arg(os_version_arg)
+        )
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Check if hashes are (probably) in a filter.")
+                .arg(filter_arg)
+                .arg(hash_arg)
+        )
+}
+
+
+/// Set the logging level from the CLI parsed parameters.
+///
+fn set_logging_level(matches: &ArgMatches){
+    // Get the logging level supplied by the user
+    let message_level = match matches.value_of("logging") {
+        Some("Off") => LevelFilter::Off,
+        Some("Error") => LevelFilter::Error,
+        Some("Warn") => LevelFilter::Warn,
+        Some("Info") => LevelFilter::Info,
+        Some("Debug") => LevelFilter::Debug,
+        Some("Trace") => LevelFilter::Trace,
+        Some(unknown) => {
+            eprintln!("Unknown log level [{}]", unknown);
+            exit(-1);
+        },
+        None => {
+            LevelFilter::Off
+        }
+    };
+
+    // Create logging with debug level that prints to stderr
+    // See https://docs.rs/fern/0.6.0/fern/#example-setup
+    let result = Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                Local::now().format("[%Y-%m-%d %H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message
+            ))
+        })
+        .level(message_level)
+        .chain(std::io::stderr())
+        .apply();
+
+    // Ensure that logger was dispatched
+    match result {
+        Ok(_) => trace!("Logging as been initialized!"),
+        Err(error) => {
+            eprintln!("Error initializing fern logging: {}", error);
+            exit(-1);
+        }
+    }
+}
+
+
+/// Build a filter file
+///
+fn build(matches: &ArgMatches) -> Result<(), String> {
+    let source = matches.value_of("source")
+        .expect("No source folder was provided.");
+    let output = matches.value_of("output")
+        .expect("No output was provided.");
+    let os_versions: Vec<String> = matches.values_of("os_version")
+        .map(|values| values.map(|v| v.to_string()).collect())
+        .unwrap_or_default();
+
+    let filter = HashFilterBuilder::from_vanilla(source, &os_versions)?
+        .build()?;
+    filter.to_path(output)?;
+
+    info!(
+        "Added {} keys from {} OS versions to {}",
+        filter.header().key_count,
+        filter.header().os_versions.len(),
+        output
+    );
+
+    Ok(())
+}
+
+
+/// Check hashes against a filter file. Each hash is printed with `true` if it
+/// is (probably) in the filter.
+///
+fn check(matches: &ArgMatches) -> Result<(), String> {
+    let filter = matches.value_of("filter")
+        .expect("No filter was provided.");
+    let filter = HashFilter::from_path(filter)?;
+
+    for hash in matches.values_of("hashes").into_iter().flatten() {
+        match filter.contains(hash) {
+            Ok(known) => println!("{}\t{}", hash, known),
+            Err(e) => error!("{}", e)
+        }
+    }
+
+    Ok(())
+}
+
+
+/// The main entry point for this tool.
+///
+fn main() {
+    let arg_parser = get_argument_parser();
+    let options = arg_parser.get_matches();
+
+    set_logging_level(&options);
+
+    let result = match options.subcommand() {
+        ("build", Some(matches)) => build(matches),
+        ("check", Some(matches)) => check(matches),
+        _ => Err("A subcommand (build or check) is required.".to_string())
+    };
+
+    if let Err(e) = result {
+        eprintln!("{}", e);
+        exit(-1);
+    }
+}
@@ -10,6 +10,7 @@ use tantivy::directory::MmapDirectory;
 use winvanilla::index::{generate_schema_from_vanilla, WindowRefIndexWriter};
 
 #[cfg(all(windows))]
+#[allow(clippy::non_minimal_cfg)]
 #[global_allocator]
 static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
 
@@ -45,6 +46,11 @@ fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
         .takes_value(true)
         .help("The total target memory usage that will be split between writer threads.");
 
+    let incremental_arg = Arg::with_name("incremental")
+        .long("incremental")
+        .takes_value(false)
+        .help("Only index new or changed data sets and remove data sets that no longer exist.");
+
     let logging_arg = Arg::with_name("logging")
         .long("logging")
         .value_name("LOGGING LEVEL")
@@ -60,6 +66,7 @@ fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
         .arg(source_arg)
         .arg(index_arg)
         .arg(overall_memory_arg)
+        .arg(incremental_arg)
         .arg(logging_arg)
 }
 
@@ -148,6 +155,10 @@ fn main() {
     let mut writer = WindowRefIndexWriter::from_index(source, index, overall_memory)
         .expect("Error creating WindowRefIndexWriter!");
 
-    writer.delete_all_documents(true).expect("Error deleting documents!");
-    writer.index_mt().expect("Error indexing documents!");
+    if options.is_present("incremental") {
+        writer.index_incremental().expect("Error incrementally indexing documents!");
+    } else {
+        writer.delete_all_documents(true).expect("Error deleting documents!");
+        writer.index_mt().expect("Error indexing documents!");
+    }
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
+// fast-alloc is not declared in Cargo.toml
+#![allow(unexpected_cfgs)]
 #[macro_use] extern crate rocket;
 use std::process::exit;
 use std::path::Path;
@@ -6,6 +8,7 @@ use std::str::FromStr;
 use std::path::PathBuf;
 use rocket::{State};
 use rocket::config::Config;
+use rocket::data::{Limits, ToByteUnit};
 use clap::{App, Arg, ArgMatches};
 use chrono::Local;
 use fern::Dispatch;
@@ -19,8 +22,15 @@ use winvanilla::index::{
     WindowsRefIndexReader,
     WindowRefIndexWriter
 };
-use winvanilla::service::path::{known_file_name, known_full_name, lookup_file_name, lookup_full_name};
-use winvanilla::service::hash::lookup_hash;
+use winvanilla::service::path::{
+    known_file_name, known_full_name,
+    lookup_file_name, lookup_full_name,
+    verdict_full_name, history_full_name,
+    similar_file_name
+};
+use winvanilla::service::hash::{lookup_hash, lookup_hash_batch};
+use winvanilla::service::search::search_glob;
+use winvanilla::service::error::{not_found, unprocessable_entity, default_catcher};
 
 #[cfg(all(feature = "fast-alloc", not(windows)))]
 use jemallocator::Jemalloc;
@@ -244,6 +254,9 @@ fn rocket() -> _ {
     config.port = port;
     // Set address
     config.address = address;
+    // Batch lookups can contain tens of thousands of hashes
+    config.limits = Limits::default()
+        .limit("json", 16.mebibytes());
 
     rocket::custom(config)
         .manage(reader)
@@ -251,6 +264,12 @@ fn rocket() -> _ {
             index,
             known_file_name, known_full_name,
             lookup_file_name, lookup_full_name,
-            lookup_hash
+            verdict_full_name, history_full_name,
+            similar_file_name,
+            lookup_hash, lookup_hash_batch,
+            search_glob
+        ])
+        .register("/", catchers![
+            not_found, unprocessable_entity, default_catcher
         ])
 }
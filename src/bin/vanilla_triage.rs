@@ -0,0 +1,300 @@
+#[macro_use] extern crate log;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::process::exit;
+use chrono::Local;
+use fern::Dispatch;
+use log::LevelFilter;
+use clap::{App, Arg, ArgMatches};
+use tantivy::Index;
+use tantivy::directory::MmapDirectory;
+use winvanilla::index::{LookupFilter, WindowsRefIndexReader};
+use winvanilla::record::RecordSource;
+use winvanilla::triage::{ReportFormat, ReportWriter, Triage, TriageSummary};
+use winvanilla::jsonl::{FieldMapping, JsonlRecordSource};
+use winvanilla::mftecmd::MftECmdRecordIterator;
+use winvanilla::vanilla::CsvFileRecordIterator;
+
+#[cfg(windows)]
+#[global_allocator]
+static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
+
+static VERSION: &str = env!("CARGO_PKG_VERSION");
+
+
+/// Create and return an App that is used to parse the command line params
+/// that were specified by the user.
+///
+fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
+    let index_arg = Arg::with_name("index_location")
+        .short("-i")
+        .long("index-location")
+        .required(true)
+        .value_name("INDEX_LOCATION")
+        .takes_value(true)
+        .help("The index folder");
+
+    let file_list_arg = Arg::with_name("file_list")
+        .short("-f")
+        .long("file-list")
+        .required(true)
+        .value_name("FILE_LIST")
+        .takes_value(true)
+        .help("The host's file listing.");
+
+    let input_format_arg = Arg::with_name("input_format")
+        .long("input-format")
+        .value_name("INPUT_FORMAT")
+        .takes_value(true)
+        .default_value("powershell")
+        .possible_values(&["powershell", "mftecmd", "jsonl"])
+        .help("The format of the file listing (powershell csv, MFTECmd $MFT csv or jsonl).");
+
+    let preset_arg = Arg::with_name("preset")
+        .long("preset")
+        .value_name("PRESET")
+        .takes_value(true)
+        .default_value("powershell")
+        .possible_values(&["powershell", "velociraptor", "kape"])
+        .help("The jsonl field mapping preset.");
+
+    let field_map_arg = Arg::with_name("field_map")
+        .long("field-map")
+        .value_name("FIELD_MAP")
+        .takes_value(true)
+        .help("Override jsonl field mappings of the preset (e.g. 'path=OSPath,size=Size,hash=Hash.SHA256'). \
+        Keys are path, name, directory, size, hash and skip. Fields can be dotted paths.");
+
+    let output_arg = Arg::with_name("output")
+        .short("-o")
+        .long("output")
+        .required(false)
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .help("The report file (otherwise the report is written to stdout).");
+
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .default_value("jsonl")
+        .possible_values(&["jsonl", "csv"])
+        .help("The report format.");
+
+    let os_name_arg = Arg::with_name("os_name")
+        .long("os-name")
+        .value_name("OS_NAME")
+        .takes_value(true)
+        .help("Only compare against this OsName (e.g. 'Microsoft Windows 10 Pro').");
+
+    let os_version_arg = Arg::with_name("os_version")
+        .long("os-version")
+        .value_name("OS_VERSION")
+        .takes_value(true)
+        .help("Only compare against this OsVersion (e.g. '10.0.18363 N/A Build 18363').");
+
+    let min_build_arg = Arg::with_name("min_build")
+        .long("min-build")
+        .value_name("BUILD")
+        .takes_value(true)
+        .help("Only compare against builds greater than or equal to this build.");
+
+    let max_build_arg = Arg::with_name("max_build")
+        .long("max-build")
+        .value_name("BUILD")
+        .takes_value(true)
+        .help("Only compare against builds less than or equal to this build.");
+
+    let logging_arg = Arg::with_name("logging")
+        .long("logging")
+        .value_name("LOGGING LEVEL")
+        .takes_value(true)
+        .default_value("Info")
+        .possible_values(&["Off", "Error", "Warn", "Info", "Debug", "Trace"])
+        .help("Logging level to use.");
+
+    App::new("vanilla_triage")
+        .version(VERSION)
+        .author("Matthew Seyer <https://github.com/forensicmatt/VanillaWindowsTools>")
+        .about("Check a host file listing against a VanillaWindowsReference index.")
+        .arg(index_arg)
+        .arg(file_list_arg)
+        .arg(input_format_arg)
+        .arg(preset_arg)
+        .arg(field_map_arg)
+        .arg(output_arg)
+        .arg(format_arg)
+        .arg(os_name_arg)
+        .arg(os_version_arg)
+        .arg(min_build_arg)
+        .arg(max_build_arg)
+        .arg(logging_arg)
+}
+
+
+/// Set the logging level from the CLI parsed parameters.
+///
+fn set_logging_level(matches: &ArgMatches){
+    // Get the logging level supplied by the user
+    let message_level = match matches.value_of("logging") {
+        Some("Off") => LevelFilter::Off,
+        Some("Error") => LevelFilter::Error,
+        Some("Warn") => LevelFilter::Warn,
+        Some("Info") => LevelFilter::Info,
+        Some("Debug") => LevelFilter::Debug,
+        Some("Trace") => LevelFilter::Trace,
+        Some(unknown) => {
+            eprintln!("Unknown log level [{}]", unknown);
+            exit(-1);
+        },
+        None => {
+            LevelFilter::Off
+        }
+    };
+
+    // Create logging with debug level that prints to stderr
+    // See https://docs.rs/fern/0.6.0/fern/#example-setup
+    let result = Dispatch::new()
+        .format(|out, message, record| {
+            out.finish(format_args!(
+                "{}[{}][{}] {}",
+                Local::now().format("[%Y-%m-%d %H:%M:%S]"),
+                record.target(),
+                record.level(),
+                message
+            ))
+        })
+        .level(message_level)
+        .chain(std::io::stderr())
+        .apply();
+
+    // Ensure that logger was dispatched
+    match result {
+        Ok(_) => trace!("Logging as been initialized!"),
+        Err(error) => {
+            eprintln!("Error initializing fern logging: {}", error);
+            exit(-1);
+        }
+    }
+}
+
+
+/// Get the LookupFilter from the CLI parsed parameters.
+///
+fn get_lookup_filter(matches: &ArgMatches) -> LookupFilter {
+    let build = |name: &str| matches.value_of(name)
+        .map(|v| v.parse::<u64>().unwrap_or_else(|_| {
+            eprintln!("Unable to parse {} as u64: {}", name, v);
+            exit(-1);
+        }));
+
+    LookupFilter {
+        os_name: matches.value_of("os_name").map(|v| v.to_string()),
+        os_version: matches.value_of("os_version").map(|v| v.to_string()),
+        min_build: build("min_build"),
+        max_build: build("max_build"),
+        ..Default::default()
+    }
+}
+
+
+/// Get the RecordSource of a file listing from the CLI parsed parameters.
+///
+fn get_record_source(matches: &ArgMatches) -> Result<Box<dyn RecordSource>, String> {
+    let file_list = matches.value_of("file_list")
+        .expect("No file_list provided.");
+
+    match matches.value_of("input_format") {
+        Some("powershell") => Ok(Box::new(CsvFileRecordIterator::from_path(file_list)?)),
+        Some("mftecmd") => Ok(Box::new(MftECmdRecordIterator::from_path(file_list)?)),
+        Some("jsonl") => {
+            let mut mapping = FieldMapping::from_preset(
+                matches.value_of("preset").unwrap_or("powershell")
+            )?;
+            if let Some(field_map) = matches.value_of("field_map") {
+                mapping.update(field_map)?;
+            }
+            Ok(Box::new(JsonlRecordSource::from_path(file_list, mapping)?))
+        },
+        other => Err(format!("Unknown input format {:?}", other))
+    }
+}
+
+
+/// The main entry point for this tool.
+///
+fn main() {
+    let arg_parser = get_argument_parser();
+    let options = arg_parser.get_matches();
+
+    set_logging_level(&options);
+
+    let filter = get_lookup_filter(&options);
+    if let Err(e) = filter.validate() {
+        eprintln!("{}", e);
+        exit(-1);
+    }
+
+    let format: ReportFormat = options.value_of("format")
+        .expect("No format provided.")
+        .parse()
+        .expect("Unable to parse format.");
+
+    let index_location = options.value_of("index_location")
+        .expect("No index_location provided.");
+    let index_directory = MmapDirectory::open(index_location)
+        .expect("Error opening index_location");
+    let index = Index::open(index_directory)
+        .expect("Error opening index.");
+    let index_reader = WindowsRefIndexReader::try_from(index)
+        .expect("Error creating WindowsRefIndexReader.");
+
+    let file_list = options.value_of("file_list")
+        .expect("No file_list provided.");
+    let mut source = get_record_source(&options)
+        .expect("Error opening file_list.");
+
+    let output: Box<dyn Write> = match options.value_of("output") {
+        Some(path) => Box::new(
+            File::create(path).expect("Error creating output file.")
+        ),
+        None => Box::new(std::io::stdout())
+    };
+    let mut report = ReportWriter::new(BufWriter::new(output), format)
+        .expect("Error creating report.");
+
+    let mut triage = Triage::new(&index_reader, filter);
+    let mut summary = TriageSummary::default();
+    while let Some(result) = source.next_record() {
+        let record = match result {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Error reading {}: {}", file_list, e);
+                summary.errors += 1;
+                continue;
+            }
+        };
+
+        let triage_result = match triage.triage_record(&record) {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Error checking {}: {}", record.full_name, e);
+                summary.errors += 1;
+                continue;
+            }
+        };
+
+        summary.add(triage_result.verdict.verdict);
+        report.write_result(&triage_result)
+            .expect("Error writing report.");
+    }
+
+    report.flush()
+        .expect("Error writing report.");
+
+    info!(
+        "Vanilla: {}, MasqueradeCandidate: {}, HashMismatch: {}, Unknown: {}, Errors: {}",
+        summary.vanilla, summary.masquerade_candidate,
+        summary.hash_mismatch, summary.unknown, summary.errors
+    );
+}
@@ -3,10 +3,17 @@ use std::process::exit;
 use chrono::Local;
 use fern::Dispatch;
 use log::LevelFilter;
+use std::fs::File;
+use std::io::{stdout, BufWriter, Write};
+use std::path::Path;
 use clap::{App, Arg, ArgMatches};
-use winvanilla::vanilla::WinFileListIterator;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use winvanilla::output::{RecordFormat, RecordWriter};
+use winvanilla::vanilla::{DEFAULT_EXCLUDE_LIST, WinFileListIterator};
 
 #[cfg(all(windows))]
+#[allow(clippy::non_minimal_cfg)]
 #[global_allocator]
 static ALLOC: rpmalloc::RpMalloc = rpmalloc::RpMalloc;
 
@@ -25,6 +32,47 @@ fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
         .takes_value(true)
         .help("The source folder");
 
+    let format_arg = Arg::with_name("format")
+        .long("format")
+        .value_name("FORMAT")
+        .takes_value(true)
+        .default_value("jsonl")
+        .possible_values(&["jsonl", "csv", "tsv", "columns"])
+        .help("The output format. 'columns' is a single JSON object with an array of values per column.");
+
+    let fields_arg = Arg::with_name("fields")
+        .long("fields")
+        .value_name("FIELDS")
+        .takes_value(true)
+        .use_delimiter(true)
+        .help("Comma separated list of the fields to output (in this order). Overrides the exclude list.");
+
+    let exclude_arg = Arg::with_name("exclude")
+        .long("exclude")
+        .value_name("FIELDS")
+        .takes_value(true)
+        .use_delimiter(true)
+        .help("Comma separated list of fields to leave out.");
+
+    let timestamps_arg = Arg::with_name("include_timestamps")
+        .long("include-timestamps")
+        .help("Include the LastAccessTimeUtc and LastWriteTimeUtc fields.");
+
+    let sddl_arg = Arg::with_name("include_sddl")
+        .long("include-sddl")
+        .help("Include the Sddl field.");
+
+    let output_arg = Arg::with_name("output")
+        .short("-o")
+        .long("output")
+        .value_name("OUTPUT")
+        .takes_value(true)
+        .help("The file to write to. Defaults to stdout.");
+
+    let gzip_arg = Arg::with_name("gzip")
+        .long("gzip")
+        .help("Gzip the output. This is the default for outputs ending in '.gz'.");
+
     let logging_arg = Arg::with_name("logging")
         .long("logging")
         .value_name("LOGGING LEVEL")
@@ -38,6 +86,13 @@ fn get_argument_parser<'a, 'b>() -> App<'a, 'b> {
         .author("Matthew Seyer <https://github.com/forensicmatt/VanillaWindowsTools>")
         .about("Transform VanillaWindowsReference csv files into JSONL for backends.")
         .arg(source_arg)
+        .arg(format_arg)
+        .arg(fields_arg)
+        .arg(exclude_arg)
+        .arg(timestamps_arg)
+        .arg(sddl_arg)
+        .arg(output_arg)
+        .arg(gzip_arg)
         .arg(logging_arg)
 }
 
@@ -89,28 +144,130 @@ fn set_logging_level(matches: &ArgMatches){
 }
 
 
-/// The main entry point for this tool.
+/// Get the fields to leave out of the records. This is the default exclude
+/// list, less the included fields, plus the --exclude fields.
 ///
-fn main() {
-    let arg_parser = get_argument_parser();
-    let options = arg_parser.get_matches();
+fn get_exclude_list(matches: &ArgMatches) -> Vec<String> {
+    let mut exclude: Vec<String> = DEFAULT_EXCLUDE_LIST.iter()
+        .filter(|column| match **column {
+            "LastAccessTimeUtc" | "LastWriteTimeUtc" => !matches.is_present("include_timestamps"),
+            "Sddl" => !matches.is_present("include_sddl"),
+            _ => true
+        })
+        .map(|column| column.to_string())
+        .collect();
+    exclude.extend(
+        matches.values_of("exclude")
+            .into_iter()
+            .flatten()
+            .map(|column| column.to_string())
+    );
+    exclude
+}
 
-    set_logging_level(&options);
 
-    let source = options.value_of("source")
+/// Get the output columns. These are the --fields, or else every field of the
+/// file lists that is not excluded (in the order they are first seen).
+///
+fn get_columns(source: &str, exclude: &[String], matches: &ArgMatches) -> Vec<String> {
+    if let Some(fields) = matches.values_of("fields") {
+        return fields.map(|field| field.to_string()).collect();
+    }
+
+    let mut columns: Vec<String> = Vec::new();
+    for (location, file_list) in WinFileListIterator::from_path(source) {
+        let rec_iter = match file_list.into_iter() {
+            Ok(i) => i,
+            Err(e) => {
+                error!("{}: {}", location.to_string_lossy(), e);
+                continue;
+            }
+        };
+        for column in rec_iter.get_headers() {
+            if !exclude.contains(&column) && !columns.contains(&column) {
+                columns.push(column);
+            }
+        }
+    }
+    columns
+}
+
+
+/// Write the records of every file list in the source.
+///
+fn write_records<W: Write>(matches: &ArgMatches, writer: W) -> Result<W, String> {
+    let source = matches.value_of("source")
         .expect("No source folder was provided.");
+    let format: RecordFormat = matches.value_of("format")
+        .expect("No format was provided.")
+        .parse()?;
+
+    // Selected fields are output even if they are in the exclude list
+    let exclude = if matches.is_present("fields") {
+        Vec::new()
+    } else {
+        get_exclude_list(matches)
+    };
+    let columns = get_columns(source, &exclude, matches);
+    let mut record_writer = RecordWriter::new(writer, format, columns)?;
 
     let iter = WinFileListIterator::from_path(source);
     for (location, file_list) in iter {
-        let rec_iter = match file_list.into_iter() {
+        let mut rec_iter = match file_list.into_iter() {
             Ok(i) => i,
             Err(e) => {
                 error!("{}: {}", location.to_string_lossy(), e);
                 continue;
             }
         };
+        rec_iter.set_exclude_list(exclude.clone());
         for record in rec_iter {
-            println!("{}", record);
+            record_writer.write_record(&record)?;
         }
     }
-}
\ No newline at end of file
+
+    record_writer.finish()
+}
+
+
+/// Write the records to the output, gzipped if requested.
+///
+fn write_output(matches: &ArgMatches) -> Result<(), String> {
+    let output = matches.value_of("output");
+    let gzip = matches.is_present("gzip") ||
+        output.map(|o| o.to_lowercase().ends_with(".gz")).unwrap_or(false);
+
+    let writer: Box<dyn Write> = match output {
+        Some(path) => {
+            let fh = File::create(Path::new(path))
+                .map_err(|e| format!("Could not create '{}'. {:?}", path, e))?;
+            Box::new(BufWriter::new(fh))
+        },
+        None => Box::new(stdout())
+    };
+
+    if gzip {
+        write_records(matches, GzEncoder::new(writer, Compression::default()))?
+            .finish()
+            .map_err(|e| format!("{:?}", e))?;
+    } else {
+        write_records(matches, writer)?;
+    }
+
+    Ok(())
+}
+
+
+/// The main entry point for this tool.
+///
+fn main() {
+    let arg_parser = get_argument_parser();
+    let options = arg_parser.get_matches();
+
+    set_logging_level(&options);
+
+    if let Err(e) = write_output(&options) {
+        eprintln!("{}", e);
+        exit(-1);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::Write;
+use serde::Serialize;
+use serde_json::Value;
+use crate::error::VanillaError;
+use crate::index::{LookupFilter, WindowsRefIndexReader};
+use crate::triage::ReportFormat;
+use crate::vanilla::WindowsFileList;
+
+/// The columns of a CSV diff report
+const CSV_DIFF_HEADER: &[&str] = &[
+    "Change", "FullName", "OldLength", "NewLength", "OldMD5", "NewMD5", "OldSHA256", "NewSHA256"
+];
+
+
+/// A file of a build as it is compared.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileState {
+    #[serde(rename = "Length")]
+    pub length: Option<u64>,
+    #[serde(rename = "MD5")]
+    pub md5: Option<String>,
+    #[serde(rename = "SHA256")]
+    pub sha256: Option<String>,
+}
+impl FileState {
+    /// Check if a file changed between builds. Hashes are only compared when
+    /// both builds have them.
+    fn is_modified(&self, new: &FileState) -> bool {
+        if self.length != new.length {
+            return true;
+        }
+        match (&self.sha256, &new.sha256) {
+            (Some(old), Some(new)) => !old.eq_ignore_ascii_case(new),
+            _ => false
+        }
+    }
+}
+
+
+/// How a file changed between builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Change {
+    Added,
+    Removed,
+    Modified,
+}
+
+
+/// A file that changed between builds.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileDiff {
+    #[serde(rename = "Change")]
+    pub change: Change,
+    /// Without the drive letter (e.g. `Windows\System32\cmd.exe`)
+    #[serde(rename = "FullName")]
+    pub full_name: String,
+    #[serde(rename = "Old")]
+    pub old: Option<FileState>,
+    #[serde(rename = "New")]
+    pub new: Option<FileState>,
+}
+impl FileDiff {
+    /// The values of the diff in the order of the CSV diff header
+    fn csv_row(&self) -> Vec<String> {
+        let get = |state: &Option<FileState>, f: fn(&FileState) -> Option<String>| {
+            state.as_ref()
+                .and_then(f)
+                .unwrap_or_default()
+        };
+        vec![
+            format!("{:?}", self.change),
+            self.full_name.clone(),
+            get(&self.old, |s| s.length.map(|v| v.to_string())),
+            get(&self.new, |s| s.length.map(|v| v.to_string())),
+            get(&self.old, |s| s.md5.clone()),
+            get(&self.new, |s| s.md5.clone()),
+            get(&self.old, |s| s.sha256.clone()),
+            get(&self.new, |s| s.sha256.clone()),
+        ]
+    }
+}
+
+
+/// The files of a build by their lowercase full name (without the drive letter).
+/// The full name is kept as it was listed.
+pub type BuildFiles = BTreeMap<String, (String, FileState)>;
+
+
+/// The differences between two builds, ordered by full name.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuildDiff {
+    #[serde(rename = "Added")]
+    pub added: usize,
+    #[serde(rename = "Removed")]
+    pub removed: usize,
+    #[serde(rename = "Modified")]
+    pub modified: usize,
+    #[serde(rename = "Unchanged")]
+    pub unchanged: usize,
+    #[serde(rename = "Changes")]
+    pub changes: Vec<FileDiff>,
+}
+impl BuildDiff {
+    /// Compare the files of an old and a new build. Full names are compared case
+    /// insensitive and a file is modified when its SHA256 or Length changed.
+    pub fn from_files(old: BuildFiles, mut new: BuildFiles) -> Self {
+        let mut diff = Self::default();
+
+        for (key, (full_name, old_state)) in old {
+            match new.remove(&key) {
+                Some((_, new_state)) if old_state.is_modified(&new_state) => {
+                    diff.modified += 1;
+                    diff.changes.push(FileDiff {
+                        change: Change::Modified,
+                        full_name,
+                        old: Some(old_state),
+                        new: Some(new_state)
+                    });
+                },
+                Some(_) => diff.unchanged += 1,
+                None => {
+                    diff.removed += 1;
+                    diff.changes.push(FileDiff {
+                        change: Change::Removed,
+                        full_name,
+                        old: Some(old_state),
+                        new: None
+                    });
+                }
+            }
+        }
+
+        for (_key, (full_name, new_state)) in new {
+            diff.added += 1;
+            diff.changes.push(FileDiff {
+                change: Change::Added,
+                full_name,
+                old: None,
+                new: Some(new_state)
+            });
+        }
+
+        diff.changes.sort_by_key(|c| c.full_name.to_lowercase());
+        diff
+    }
+
+    /// Write the changes in a ReportFormat (one change per line/row)
+    pub fn write_changes(&self, writer: impl Write, format: ReportFormat) -> Result<(), String> {
+        match format {
+            ReportFormat::Jsonl => {
+                let mut writer = writer;
+                for change in &self.changes {
+                    let line = serde_json::to_string(change)
+                        .map_err(|e| format!("{:?}", e))?;
+                    writeln!(writer, "{}", line)
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+                writer.flush()
+                    .map_err(|e| format!("{:?}", e))
+            },
+            ReportFormat::Csv => {
+                let mut writer = csv::Writer::from_writer(writer);
+                writer.write_record(CSV_DIFF_HEADER)
+                    .map_err(|e| format!("{:?}", e))?;
+                for change in &self.changes {
+                    writer.write_record(change.csv_row())
+                        .map_err(|e| format!("{:?}", e))?;
+                }
+                writer.flush()
+                    .map_err(|e| format!("{:?}", e))
+            }
+        }
+    }
+}
+
+
+/// Get the files of a data set folder. Records that can't be read are logged
+/// and skipped.
+pub fn get_file_list_files(file_list: &WindowsFileList) -> Result<BuildFiles, String> {
+    let mut files = BuildFiles::new();
+    for record in file_list.into_record_iter()? {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Error reading record in {}: {}", file_list.file_list_path().to_string_lossy(), e);
+                continue;
+            }
+        };
+
+        let full_name = record.full_name.relative_path().to_string();
+        files.entry(full_name.to_lowercase())
+            .or_insert((full_name, FileState {
+                length: Some(record.length),
+                md5: record.md5,
+                sha256: record.sha256
+            }));
+    }
+    Ok(files)
+}
+
+
+/// Get the files of a data set in the index. `build` is a DataSetPath (e.g.
+/// `Windows10/20H2/W10_20H2_Pro_19042.631`) or an OS version (e.g. `10.0.19042
+/// N/A Build 19042`). Errors out if an OS version is in more than one data set
+/// (such as two editions or UBRs), since their files can't be told apart.
+pub fn get_index_files(
+    index_reader: &WindowsRefIndexReader,
+    build: &str
+) -> Result<BuildFiles, VanillaError> {
+    let filter = LookupFilter {
+        data_set_path: Some(build.to_owned()),
+        ..Default::default()
+    };
+    let (mut files, _) = read_index_files(index_reader, &filter)?;
+    if files.is_empty() {
+        let filter = LookupFilter {
+            os_version: Some(build.to_owned()),
+            ..Default::default()
+        };
+        let (os_version_files, data_sets) = read_index_files(index_reader, &filter)?;
+        if data_sets.len() > 1 {
+            return Err(VanillaError::invalid_input(format!(
+                "OS version '{}' is in {} data sets, use a DataSetPath instead: {}",
+                build,
+                data_sets.len(),
+                data_sets.into_iter().collect::<Vec<_>>().join(", ")
+            )));
+        }
+        files = os_version_files;
+    }
+
+    Ok(files)
+}
+
+
+/// Read the files of the documents that match a filter, along with the data
+/// sets they came from.
+fn read_index_files(
+    index_reader: &WindowsRefIndexReader,
+    filter: &LookupFilter
+) -> Result<(BuildFiles, BTreeSet<String>), VanillaError> {
+    let get_str = |hit: &Value, field: &str| hit.get(field)
+        .and_then(|v| v.get(0))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_owned());
+    let get_u64 = |hit: &Value, field: &str| hit.get(field)
+        .and_then(|v| v.get(0))
+        .and_then(|v| v.as_u64());
+
+    let mut files = BuildFiles::new();
+    let mut data_sets = BTreeSet::new();
+    index_reader.for_each_doc(filter, |hit| {
+        if let Some(data_set) = get_str(&hit, "DataSetPath") {
+            data_sets.insert(data_set);
+        }
+        let full_name = match get_str(&hit, "FullName") {
+            Some(f) => f,
+            None => return
+        };
+        files.entry(full_name.to_lowercase())
+            .or_insert((full_name, FileState {
+                length: get_u64(&hit, "Length"),
+                md5: get_str(&hit, "MD5"),
+                sha256: get_str(&hit, "SHA256")
+            }));
+    })?;
+
+    Ok((files, data_sets))
+}
+
+
+/// Diff two data set folders.
+pub fn diff_file_lists(old: &WindowsFileList, new: &WindowsFileList) -> Result<BuildDiff, String> {
+    Ok(BuildDiff::from_files(
+        get_file_list_files(old)?,
+        get_file_list_files(new)?
+    ))
+}
+
+
+/// Diff two data sets of the index, each a DataSetPath or an OS version (see
+/// `get_index_files`). Errors out if a build is not in the index.
+pub fn diff_index_versions(
+    index_reader: &WindowsRefIndexReader,
+    old_version: &str,
+    new_version: &str
+) -> Result<BuildDiff, VanillaError> {
+    let mut builds = Vec::with_capacity(2);
+    for build in [old_version, new_version] {
+        let files = get_index_files(index_reader, build)?;
+        if files.is_empty() {
+            return Err(VanillaError::invalid_input(
+                format!("Data set or OS version '{}' is not in the index", build)
+            ));
+        }
+        builds.push(files);
+    }
+
+    let new = builds.pop().unwrap_or_default();
+    let old = builds.pop().unwrap_or_default();
+    Ok(BuildDiff::from_files(old, new))
+}
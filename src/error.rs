@@ -1,31 +1,97 @@
+use std::fmt;
 use tantivy::TantivyError;
 use git2::Error as GitError;
 
 
+/// The kind of a VanillaError. The service uses this to pick the HTTP status.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// The input was not valid (e.g. a hash with an unhandled length)
+    InvalidInput,
+    /// A query could not be parsed
+    InvalidQuery,
+    /// The requested item does not exist
+    NotFound,
+    /// Errors from the index
+    Index,
+    /// Errors from IO operations
+    Io,
+    /// Errors from git operations
+    Git,
+    /// Everything else
+    Other,
+}
+impl ErrorKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::InvalidInput => "InvalidInput",
+            ErrorKind::InvalidQuery => "InvalidQuery",
+            ErrorKind::NotFound => "NotFound",
+            ErrorKind::Index => "IndexError",
+            ErrorKind::Io => "IoError",
+            ErrorKind::Git => "GitError",
+            ErrorKind::Other => "Error",
+        }
+    }
+}
+
+
 #[derive(Debug)]
 pub struct VanillaError {
+    kind: ErrorKind,
     message: String
 }
 impl VanillaError {
+    pub fn new(kind: ErrorKind, message: String) -> Self {
+        Self { kind, message }
+    }
+
     pub fn from_message(message: String) -> Self {
-        Self { message }
+        Self::new(ErrorKind::Other, message)
+    }
+
+    pub fn invalid_input(message: String) -> Self {
+        Self::new(ErrorKind::InvalidInput, message)
+    }
+
+    pub fn invalid_query(message: String) -> Self {
+        Self::new(ErrorKind::InvalidQuery, message)
+    }
+
+    pub fn not_found(message: String) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
     }
 }
 
+impl fmt::Display for VanillaError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for VanillaError {}
+
 impl From<GitError> for VanillaError {
     fn from(err: GitError) -> Self {
-        Self { message: format!("{:?}", err) }
+        Self::new(ErrorKind::Git, format!("{:?}", err))
     }
 }
 
 impl From<TantivyError> for VanillaError {
     fn from(err: TantivyError) -> Self {
-        Self { message: format!("{:?}", err) }
+        Self::new(ErrorKind::Index, format!("{:?}", err))
     }
 }
 
 impl From<std::io::Error> for VanillaError {
     fn from(err: std::io::Error) -> Self {
-        Self { message: format!("{:?}", err) }
+        Self::new(ErrorKind::Io, format!("{:?}", err))
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,154 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use std::collections::{BTreeMap, HashSet};
+use std::collections::btree_map::Entry;
+use std::path::Path;
+use crate::export::{for_each_record, ExportStats};
+
+
+/// The formats of a hash list export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HashListFormat {
+    /// A hashdeep audit file (size, md5, sha256 and file name)
+    Hashdeep,
+    /// `md5sum` output
+    Md5sum,
+    /// `sha256sum` output
+    Sha256sum,
+    /// `md5sum` output sorted by hash with one name per hash, for `hfind -i md5sum`
+    HfindMd5,
+}
+impl FromStr for HashListFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "hashdeep" => Ok(Self::Hashdeep),
+            "md5sum" => Ok(Self::Md5sum),
+            "sha256sum" => Ok(Self::Sha256sum),
+            "hfind-md5" => Ok(Self::HfindMd5),
+            other => Err(format!("Unknown hash list format '{}'", other))
+        }
+    }
+}
+impl fmt::Display for HashListFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Hashdeep => write!(f, "hashdeep"),
+            Self::Md5sum => write!(f, "md5sum"),
+            Self::Sha256sum => write!(f, "sha256sum"),
+            Self::HfindMd5 => write!(f, "hfind-md5"),
+        }
+    }
+}
+
+
+/// Format a line the way md5sum/sha256sum do. Names with a backslash or newline
+/// are escaped and the line is prefixed with a backslash.
+pub fn get_checksum_line(hash: &str, name: &str) -> String {
+    if name.contains(['\\', '\n']) {
+        let name = name.replace('\\', r"\\")
+            .replace('\n', r"\n");
+        format!("\\{}  {}", hash, name)
+    } else {
+        format!("{}  {}", hash, name)
+    }
+}
+
+
+/// Get the key a hash is deduplicated on: the first 128 bits of the hash, which
+/// is all of a md5 and keeps the set small for a full reference set.
+fn get_dedup_key(hash: &str) -> Option<u128> {
+    if hash.len() < 32 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    u128::from_str_radix(&hash[..32], 16).ok()
+}
+
+
+/// Export the deduplicated hashes of a VanillaWindowsReference folder as a hash
+/// list. Only data sets matching `os_versions` are exported (all when empty).
+/// Hashes are lowercase and each hash is written once, named by the FullName of
+/// the first file seen with it. hashdeep lists are deduplicated on the sha256.
+pub fn export_hash_list(
+    path: impl AsRef<Path>,
+    os_versions: &[String],
+    format: HashListFormat,
+    mut writer: impl Write
+) -> Result<ExportStats, String> {
+    let mut stats = ExportStats::default();
+    let mut seen: HashSet<u128> = HashSet::new();
+    // hfind lists are sorted, so they are written after all data sets are read
+    let mut sorted: BTreeMap<u128, String> = BTreeMap::new();
+
+    if format == HashListFormat::Hashdeep {
+        writeln!(
+            writer,
+            "%%%% HASHDEEP-1.0\n%%%% size,md5,sha256,filename\n## Invoked from: winvanilla {}\n##",
+            env!("CARGO_PKG_VERSION")
+        ).map_err(|e| format!("{:?}", e))?;
+    }
+
+    stats.data_sets = for_each_record(path, os_versions, |_win_info, record| {
+        let md5 = record.md5.map(|h| h.to_lowercase());
+        let sha256 = record.sha256.map(|h| h.to_lowercase());
+
+        let key = match (format, &md5, &sha256) {
+            (HashListFormat::Hashdeep, Some(_), Some(sha256)) => get_dedup_key(sha256),
+            (HashListFormat::Md5sum | HashListFormat::HfindMd5, Some(md5), _) => get_dedup_key(md5),
+            (HashListFormat::Sha256sum, _, Some(sha256)) => get_dedup_key(sha256),
+            _ => None
+        };
+        let key = match key {
+            Some(key) => key,
+            None => {
+                stats.skipped += 1;
+                return Ok(());
+            }
+        };
+
+        let line = match (format, md5, sha256) {
+            (HashListFormat::Hashdeep, Some(md5), Some(sha256)) => format!(
+                "{},{},{},{}", record.length, md5, sha256, record.full_name
+            ),
+            (HashListFormat::Md5sum, Some(md5), _) => get_checksum_line(&md5, &record.full_name.to_string()),
+            (HashListFormat::Sha256sum, _, Some(sha256)) => get_checksum_line(&sha256, &record.full_name.to_string()),
+            (HashListFormat::HfindMd5, Some(_), _) => {
+                match sorted.entry(key) {
+                    Entry::Occupied(_) => stats.duplicates += 1,
+                    Entry::Vacant(entry) => {
+                        entry.insert(record.full_name.to_string());
+                        stats.files += 1;
+                    }
+                }
+                return Ok(());
+            },
+            _ => {
+                stats.skipped += 1;
+                return Ok(());
+            }
+        };
+
+        if !seen.insert(key) {
+            stats.duplicates += 1;
+            return Ok(());
+        }
+
+        writeln!(writer, "{}", line)
+            .map_err(|e| format!("{:?}", e))?;
+        stats.files += 1;
+
+        Ok(())
+    })?;
+
+    // hfind does not unescape names, so they are written as is
+    for (md5, name) in sorted {
+        writeln!(writer, "{:032x}  {}", md5, name)
+            .map_err(|e| format!("{:?}", e))?;
+    }
+    writer.flush()
+        .map_err(|e| format!("{:?}", e))?;
+
+    Ok(stats)
+}
@@ -0,0 +1,79 @@
+/// NSRL RDS (legacy text and RDSv3 SQLite) exports
+pub mod nsrl;
+/// hashdeep, md5sum/sha256sum and hfind hash list exports
+pub mod hashlist;
+
+use std::path::Path;
+use crate::vanilla::{VanillaFileRecord, WindowsInfo, WinFileListIterator};
+
+
+/// The number of data sets and files handled by an export.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ExportStats {
+    pub data_sets: usize,
+    /// Files that were written
+    pub files: usize,
+    /// Files that were already written for the same key
+    pub duplicates: usize,
+    /// Files without the hashes the export needs
+    pub skipped: usize,
+}
+
+
+/// Check if an OS version is one of `os_versions`. A filter matches the whole
+/// OsVersion (e.g. `10.0.19042 N/A Build 19042`) or its version number (e.g.
+/// `10.0.19042`), case insensitive. No filters match all versions.
+pub fn matches_os_version(version: &str, os_versions: &[String]) -> bool {
+    if os_versions.is_empty() {
+        return true;
+    }
+
+    let number = version.split_whitespace()
+        .next()
+        .unwrap_or_default();
+    os_versions.iter()
+        .any(|v| v.eq_ignore_ascii_case(version) || v.eq_ignore_ascii_case(number))
+}
+
+
+/// Call `callback` for every record of every data set in a VanillaWindowsReference
+/// folder whose OS version matches `os_versions` (see `matches_os_version`). Data
+/// sets and records that can't be read are logged and skipped. Returns the number
+/// of data sets that were read.
+pub fn for_each_record<F>(
+    path: impl AsRef<Path>,
+    os_versions: &[String],
+    mut callback: F
+) -> Result<usize, String>
+where F: FnMut(&WindowsInfo, VanillaFileRecord) -> Result<(), String>
+{
+    let mut data_sets = 0;
+    for (location, file_list) in WinFileListIterator::from_path(path) {
+        let record_iter = match file_list.into_record_iter() {
+            Ok(i) => i,
+            Err(e) => {
+                error!("Error handling file list in {}: {}", location.to_string_lossy(), e);
+                continue;
+            }
+        };
+        if !matches_os_version(&record_iter.win_info.version, os_versions) {
+            debug!("Skipping {} for its OS version", location.to_string_lossy());
+            continue;
+        }
+        data_sets += 1;
+
+        let win_info = record_iter.win_info.clone();
+        for record in record_iter {
+            let record = match record {
+                Ok(r) => r,
+                Err(e) => {
+                    error!("Error reading record in {}: {}", location.to_string_lossy(), e);
+                    continue;
+                }
+            };
+            callback(&win_info, record)?;
+        }
+    }
+
+    Ok(data_sets)
+}
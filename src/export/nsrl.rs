@@ -0,0 +1,385 @@
+use std::fs::File;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+use rusqlite::{params, Connection};
+use crate::export::{for_each_record, ExportStats};
+
+/// All of the reference is Microsoft's
+const MFG_CODE: u64 = 1;
+const MFG_NAME: &str = "Microsoft Corporation";
+const APPLICATION_TYPE: &str = "Operating System";
+
+/// The file names of the legacy RDS layout
+const LEGACY_FILE: &str = "NSRLFile.txt";
+const LEGACY_PROD: &str = "NSRLProd.txt";
+const LEGACY_OS: &str = "NSRLOS.txt";
+const LEGACY_MFG: &str = "NSRLMfg.txt";
+
+/// The RDSv3 tables
+const RDS3_SCHEMA: &str = "
+    CREATE TABLE VERSION (
+        version VARCHAR UNIQUE NOT NULL,
+        build_set VARCHAR NOT NULL,
+        build_date TIMESTAMP NOT NULL,
+        release_date TIMESTAMP NOT NULL,
+        description VARCHAR NOT NULL
+    );
+    CREATE TABLE MFG (
+        manufacturer_id INTEGER PRIMARY KEY,
+        name VARCHAR NOT NULL
+    );
+    CREATE TABLE OS (
+        operating_system_id INTEGER PRIMARY KEY,
+        name VARCHAR NOT NULL,
+        version VARCHAR NOT NULL,
+        manufacturer_id INTEGER
+    );
+    CREATE TABLE PKG (
+        package_id INTEGER PRIMARY KEY,
+        name VARCHAR NOT NULL,
+        version VARCHAR NOT NULL,
+        operating_system_id INTEGER NOT NULL,
+        manufacturer_id INTEGER NOT NULL,
+        language VARCHAR NOT NULL,
+        application_type VARCHAR NOT NULL
+    );
+    CREATE TABLE FILE (
+        sha256 VARCHAR NOT NULL,
+        sha1 VARCHAR NOT NULL,
+        md5 VARCHAR NOT NULL,
+        crc32 VARCHAR NOT NULL,
+        file_name VARCHAR NOT NULL,
+        file_size INTEGER NOT NULL,
+        package_id INTEGER NOT NULL
+    );
+";
+
+/// Indexes are created after the inserts
+const RDS3_INDEXES: &str = "
+    CREATE INDEX FILE_sha256 ON FILE(sha256);
+    CREATE INDEX FILE_md5 ON FILE(md5);
+    CREATE INDEX FILE_package_id ON FILE(package_id);
+";
+
+
+/// An operating system of the reference (OsName and OsVersion of a SystemInfo_ file).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdsOs {
+    pub code: u64,
+    pub name: String,
+    pub version: String,
+}
+
+
+/// A product (package) of the reference. Every operating system is a product.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RdsProduct {
+    pub code: u64,
+    pub name: String,
+    pub version: String,
+    pub os_code: u64,
+    /// The system locale (e.g. `en-us`)
+    pub language: String,
+}
+
+
+/// A file of a product. The reference has no SHA-1 or CRC32 values, so these are
+/// left empty.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RdsFile {
+    pub md5: String,
+    pub sha256: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub product_code: u64,
+}
+
+
+/// Writes the parts of a RDS hash set.
+pub trait RdsWriter {
+    fn write_os(&mut self, os: &RdsOs) -> Result<(), String>;
+    fn write_product(&mut self, product: &RdsProduct) -> Result<(), String>;
+    fn write_file(&mut self, file: &RdsFile) -> Result<(), String>;
+    /// Called once all values have been written
+    fn finish(&mut self) -> Result<(), String>;
+}
+
+
+/// Writes the legacy RDS layout (NSRLFile.txt, NSRLProd.txt, NSRLOS.txt and
+/// NSRLMfg.txt) into a folder.
+pub struct LegacyRdsWriter {
+    file_writer: csv::Writer<File>,
+    prod_writer: csv::Writer<File>,
+    os_writer: csv::Writer<File>,
+}
+impl LegacyRdsWriter {
+    /// Create the legacy RDS files in a folder. NSRLMfg.txt is written right away.
+    pub fn create(folder: impl AsRef<Path>) -> Result<Self, String> {
+        let folder = folder.as_ref();
+        std::fs::create_dir_all(folder)
+            .map_err(|e| format!("Could not create '{}'. {:?}", folder.to_string_lossy(), e))?;
+
+        let create = |name: &str, header: &[&str]| -> Result<csv::Writer<File>, String> {
+            let path = folder.join(name);
+            let mut writer = csv::WriterBuilder::new()
+                .quote_style(csv::QuoteStyle::NonNumeric)
+                .from_path(&path)
+                .map_err(|e| format!("Could not create '{}'. {:?}", path.to_string_lossy(), e))?;
+            writer.write_record(header)
+                .map_err(|e| format!("{:?}", e))?;
+            Ok(writer)
+        };
+
+        let mut mfg_writer = create(LEGACY_MFG, &["MfgCode", "MfgName"])?;
+        mfg_writer.write_record([MFG_CODE.to_string().as_str(), MFG_NAME])
+            .and_then(|_| mfg_writer.flush().map_err(csv::Error::from))
+            .map_err(|e| format!("{:?}", e))?;
+
+        Ok( Self {
+            file_writer: create(LEGACY_FILE, &[
+                "SHA-1", "MD5", "CRC32", "FileName", "FileSize", "ProductCode", "OpSystemCode", "SpecialCode"
+            ])?,
+            prod_writer: create(LEGACY_PROD, &[
+                "ProductCode", "ProductName", "ProductVersion", "OpSystemCode", "MfgCode", "Language", "ApplicationType"
+            ])?,
+            os_writer: create(LEGACY_OS, &[
+                "OpSystemCode", "OpSystemName", "OpSystemVersion", "MfgCode"
+            ])?,
+        })
+    }
+}
+impl RdsWriter for LegacyRdsWriter {
+    fn write_os(&mut self, os: &RdsOs) -> Result<(), String> {
+        self.os_writer.write_record([
+            os.code.to_string(), os.name.clone(), os.version.clone(), MFG_CODE.to_string()
+        ]).map_err(|e| format!("{:?}", e))
+    }
+
+    fn write_product(&mut self, product: &RdsProduct) -> Result<(), String> {
+        self.prod_writer.write_record([
+            product.code.to_string(),
+            product.name.clone(),
+            product.version.clone(),
+            product.os_code.to_string(),
+            MFG_CODE.to_string(),
+            product.language.clone(),
+            APPLICATION_TYPE.to_string()
+        ]).map_err(|e| format!("{:?}", e))
+    }
+
+    fn write_file(&mut self, file: &RdsFile) -> Result<(), String> {
+        self.file_writer.write_record([
+            "", &file.md5, "", &file.file_name,
+            &file.file_size.to_string(),
+            &file.product_code.to_string(),
+            // Products and operating systems share their codes
+            &file.product_code.to_string(),
+            ""
+        ]).map_err(|e| format!("{:?}", e))
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        for writer in [&mut self.file_writer, &mut self.prod_writer, &mut self.os_writer] {
+            writer.flush()
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        Ok(())
+    }
+}
+
+
+/// Writes the RDSv3 SQLite schema (FILE, PKG, OS, MFG and VERSION tables).
+pub struct Rds3Writer {
+    /// None once the database has been finished
+    connection: Option<Connection>,
+    path: PathBuf,
+    temp_path: PathBuf,
+}
+impl Rds3Writer {
+    /// Create a RDSv3 database. Errors out if the file already exists, since
+    /// the FILE rows of a second export would be added to the first. All values
+    /// are written in a single transaction into a temp file next to `path`,
+    /// which `finish` renames to `path`. An unfinished temp file is removed
+    /// when the writer is dropped.
+    pub fn create(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        if path.exists() {
+            return Err(format!("'{}' already exists", path.to_string_lossy()));
+        }
+        let file_name = path.file_name()
+            .ok_or_else(|| format!("'{}' is not a file path", path.to_string_lossy()))?;
+        let temp_path = path.with_file_name(format!("{}.tmp", file_name.to_string_lossy()));
+        // Left over from a run that was killed
+        if temp_path.exists() {
+            std::fs::remove_file(&temp_path)
+                .map_err(|e| format!("Could not remove '{}'. {:?}", temp_path.to_string_lossy(), e))?;
+        }
+
+        let connection = Connection::open(&temp_path)
+            .map_err(|e| format!("Could not open '{}'. {:?}", temp_path.to_string_lossy(), e))?;
+        let writer = Self {
+            connection: Some(connection),
+            path: path.to_path_buf(),
+            temp_path
+        };
+        writer.init()?;
+
+        Ok(writer)
+    }
+
+    fn connection(&self) -> Result<&Connection, String> {
+        self.connection.as_ref()
+            .ok_or_else(|| format!("'{}' is already finished", self.path.to_string_lossy()))
+    }
+
+    /// Create the schema, start the transaction and write the VERSION and MFG rows
+    fn init(&self) -> Result<(), String> {
+        let connection = self.connection()?;
+        connection.execute_batch(RDS3_SCHEMA)
+            .map_err(|e| format!("Error creating RDSv3 schema: {:?}", e))?;
+        connection.execute_batch("BEGIN")
+            .map_err(|e| format!("{:?}", e))?;
+
+        let now = Utc::now().to_rfc3339();
+        connection.execute(
+            "INSERT OR REPLACE INTO VERSION VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                "VanillaWindowsReference",
+                format!("winvanilla {}", env!("CARGO_PKG_VERSION")),
+                now, now,
+                "Files of clean Windows installs from the VanillaWindowsReference repo"
+            ]
+        ).map_err(|e| format!("{:?}", e))?;
+        connection.execute(
+            "INSERT OR REPLACE INTO MFG VALUES (?1, ?2)",
+            params![MFG_CODE, MFG_NAME]
+        ).map_err(|e| format!("{:?}", e))?;
+
+        Ok(())
+    }
+}
+impl Drop for Rds3Writer {
+    fn drop(&mut self) {
+        if let Some(connection) = self.connection.take() {
+            drop(connection);
+            let _ = std::fs::remove_file(&self.temp_path);
+        }
+    }
+}
+impl RdsWriter for Rds3Writer {
+    fn write_os(&mut self, os: &RdsOs) -> Result<(), String> {
+        self.connection()?.prepare_cached("INSERT OR REPLACE INTO OS VALUES (?1, ?2, ?3, ?4)")
+            .and_then(|mut stmt| stmt.execute(params![os.code, os.name, os.version, MFG_CODE]))
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    fn write_product(&mut self, product: &RdsProduct) -> Result<(), String> {
+        self.connection()?.prepare_cached("INSERT OR REPLACE INTO PKG VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)")
+            .and_then(|mut stmt| stmt.execute(params![
+                product.code, product.name, product.version, product.os_code,
+                MFG_CODE, product.language, APPLICATION_TYPE
+            ]))
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    fn write_file(&mut self, file: &RdsFile) -> Result<(), String> {
+        self.connection()?.prepare_cached("INSERT INTO FILE VALUES (?1, '', ?2, '', ?3, ?4, ?5)")
+            .and_then(|mut stmt| stmt.execute(params![
+                file.sha256, file.md5, file.file_name, file.file_size, file.product_code
+            ]))
+            .map(|_| ())
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    fn finish(&mut self) -> Result<(), String> {
+        let connection = self.connection()?;
+        connection.execute_batch(RDS3_INDEXES)
+            .and_then(|_| connection.execute_batch("COMMIT"))
+            .map_err(|e| format!("{:?}", e))?;
+
+        // The file has to be closed before it can be renamed on Windows
+        if let Some(connection) = self.connection.take() {
+            if let Err((connection, e)) = connection.close() {
+                drop(connection);
+                let _ = std::fs::remove_file(&self.temp_path);
+                return Err(format!("{:?}", e));
+            }
+        }
+        std::fs::rename(&self.temp_path, &self.path)
+            .map_err(|e| format!("Could not rename '{}' to '{}'. {:?}",
+                self.temp_path.to_string_lossy(), self.path.to_string_lossy(), e))
+    }
+}
+
+
+/// Export a VanillaWindowsReference folder as a RDS hash set. Every distinct
+/// OsName/OsVersion is an operating system and product. Files are deduplicated
+/// by hashes, name, size and product. Files without both a MD5 and SHA256 are
+/// skipped. Only data sets matching `os_versions` are exported (all when empty).
+pub fn export_rds(
+    path: impl AsRef<Path>,
+    os_versions: &[String],
+    writer: &mut dyn RdsWriter
+) -> Result<ExportStats, String> {
+    let mut stats = ExportStats::default();
+    // (OsName, OsVersion) -> code
+    let mut products: HashMap<(String, String), u64> = HashMap::new();
+    let mut seen: HashSet<RdsFile> = HashSet::new();
+
+    stats.data_sets = for_each_record(path, os_versions, |win_info, record| {
+        let key = (win_info.name.clone(), win_info.version.clone());
+        let product_code = match products.get(&key) {
+            Some(code) => *code,
+            None => {
+                let code = products.len() as u64 + 1;
+                writer.write_os(&RdsOs {
+                    code,
+                    name: win_info.name.clone(),
+                    version: win_info.version.clone(),
+                })?;
+                writer.write_product(&RdsProduct {
+                    code,
+                    name: win_info.name.clone(),
+                    version: win_info.version.clone(),
+                    os_code: code,
+                    language: win_info.system_locale.clone().unwrap_or_default(),
+                })?;
+                products.insert(key, code);
+                code
+            }
+        };
+
+        let (md5, sha256) = match (record.md5, record.sha256) {
+            (Some(md5), Some(sha256)) => (md5.to_uppercase(), sha256.to_uppercase()),
+            _ => {
+                stats.skipped += 1;
+                return Ok(());
+            }
+        };
+
+        let file = RdsFile {
+            md5,
+            sha256,
+            file_name: record.name,
+            file_size: record.length,
+            product_code
+        };
+        if seen.contains(&file) {
+            stats.duplicates += 1;
+            return Ok(());
+        }
+
+        writer.write_file(&file)?;
+        seen.insert(file);
+        stats.files += 1;
+
+        Ok(())
+    })?;
+
+    writer.finish()?;
+
+    Ok(stats)
+}
@@ -0,0 +1,203 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::collections::BTreeSet;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use xorf::{BinaryFuse16, Filter};
+use crate::export::for_each_record;
+use crate::index::get_hash_field;
+
+/// The start of every filter file
+const MAGIC: &[u8; 8] = b"WVFILTER";
+/// The version of the filter file layout
+const FORMAT_VERSION: u32 = 1;
+/// The largest json header that is read. The header only lists OS versions, so
+/// anything larger is a corrupt file.
+const MAX_HEADER_SIZE: usize = 1024 * 1024;
+/// The most bytes decoded for the filter, about 200 million keys
+const MAX_FILTER_SIZE: usize = 512 * 1024 * 1024;
+
+
+/// Get the filter key of a MD5 or SHA256 hex string. The hashes are uniformly
+/// distributed, so the first 8 bytes are used as is.
+pub fn get_hash_key(hash: &str) -> Result<u64, String> {
+    get_hash_field(hash)?;
+    if !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(format!("Invalid hash '{}'", hash));
+    }
+    u64::from_str_radix(&hash[..16], 16)
+        .map_err(|e| format!("Invalid hash '{}': {}", hash, e))
+}
+
+
+/// Describes what a filter was built from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HashFilterHeader {
+    /// The OS versions of the data sets in the filter
+    #[serde(rename = "OsVersions")]
+    pub os_versions: Vec<String>,
+    /// The number of distinct keys in the filter
+    #[serde(rename = "KeyCount")]
+    pub key_count: u64,
+    #[serde(rename = "Created")]
+    pub created: DateTime<Utc>,
+}
+
+
+/// Collects MD5/SHA256 hashes to build a HashFilter.
+#[derive(Debug, Default)]
+pub struct HashFilterBuilder {
+    keys: Vec<u64>,
+    os_versions: BTreeSet<String>,
+}
+impl HashFilterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a builder with the MD5 and SHA256 hashes of every file in a
+    /// VanillaWindowsReference folder. Only data sets matching `os_versions`
+    /// are added (all when empty).
+    pub fn from_vanilla(
+        path: impl AsRef<Path>,
+        os_versions: &[String]
+    ) -> Result<Self, String> {
+        let mut builder = Self::new();
+        for_each_record(path, os_versions, |win_info, record| {
+            builder.add_os_version(&win_info.version);
+            for hash in [record.md5, record.sha256].iter().flatten() {
+                builder.add_hash(hash)?;
+            }
+            Ok(())
+        })?;
+        Ok(builder)
+    }
+
+    /// Add a MD5 or SHA256 hash
+    pub fn add_hash(&mut self, hash: &str) -> Result<(), String> {
+        self.keys.push(get_hash_key(hash)?);
+        Ok(())
+    }
+
+    /// Add an OS version to the header
+    pub fn add_os_version(&mut self, os_version: &str) {
+        self.os_versions.insert(os_version.to_owned());
+    }
+
+    /// Build the filter
+    pub fn build(mut self) -> Result<HashFilter, String> {
+        self.keys.sort_unstable();
+        self.keys.dedup();
+
+        let filter = BinaryFuse16::try_from(&self.keys)
+            .map_err(|e| format!("Error building filter: {}", e))?;
+
+        Ok( HashFilter {
+            header: HashFilterHeader {
+                os_versions: self.os_versions.into_iter().collect(),
+                key_count: self.keys.len() as u64,
+                created: Utc::now(),
+            },
+            filter
+        })
+    }
+}
+
+
+/// A compact binary fuse filter of MD5/SHA256 hashes for membership checks
+/// without the index. There are no false negatives and the false positive
+/// rate is about 1 in 65536.
+///
+/// The file layout is the magic `WVFILTER`, the format version (u32 LE), the
+/// length (u32 LE) of the json header, the json header and the filter.
+pub struct HashFilter {
+    header: HashFilterHeader,
+    filter: BinaryFuse16,
+}
+impl HashFilter {
+    /// What the filter was built from
+    pub fn header(&self) -> &HashFilterHeader {
+        &self.header
+    }
+
+    /// Check if a MD5 or SHA256 hash is (probably) in the filter
+    pub fn contains(&self, hash: &str) -> Result<bool, String> {
+        Ok(self.filter.contains(&get_hash_key(hash)?))
+    }
+
+    /// Write the filter
+    pub fn write_to(&self, mut writer: impl Write) -> Result<(), String> {
+        let header = serde_json::to_vec(&self.header)
+            .map_err(|e| format!("{:?}", e))?;
+
+        writer.write_all(MAGIC)
+            .and_then(|_| writer.write_all(&FORMAT_VERSION.to_le_bytes()))
+            .and_then(|_| writer.write_all(&(header.len() as u32).to_le_bytes()))
+            .and_then(|_| writer.write_all(&header))
+            .map_err(|e| format!("{:?}", e))?;
+
+        let config = bincode::config::standard()
+            .with_fixed_int_encoding();
+        bincode::encode_into_std_write(&self.filter, &mut writer, config)
+            .map_err(|e| format!("Error writing filter: {}", e))?;
+
+        writer.flush()
+            .map_err(|e| format!("{:?}", e))
+    }
+
+    /// Write the filter to a file
+    pub fn to_path(&self, path: impl AsRef<Path>) -> Result<(), String> {
+        let path = path.as_ref();
+        let fh = File::create(path)
+            .map_err(|e| format!("Could not create '{}'. {:?}", path.to_string_lossy(), e))?;
+        self.write_to(BufWriter::new(fh))
+    }
+
+    /// Read a filter
+    pub fn read_from(mut reader: impl Read) -> Result<Self, String> {
+        let mut magic = [0u8; 8];
+        let mut u32_buf = [0u8; 4];
+
+        reader.read_exact(&mut magic)
+            .map_err(|e| format!("{:?}", e))?;
+        if &magic != MAGIC {
+            return Err("Not a filter file".to_string());
+        }
+
+        reader.read_exact(&mut u32_buf)
+            .map_err(|e| format!("{:?}", e))?;
+        let version = u32::from_le_bytes(u32_buf);
+        if version != FORMAT_VERSION {
+            return Err(format!("Unhandled filter format version: {}", version));
+        }
+
+        reader.read_exact(&mut u32_buf)
+            .map_err(|e| format!("{:?}", e))?;
+        let header_size = u32::from_le_bytes(u32_buf) as usize;
+        if header_size > MAX_HEADER_SIZE {
+            return Err(format!("Invalid filter header size: {}", header_size));
+        }
+        let mut header = vec![0u8; header_size];
+        reader.read_exact(&mut header)
+            .map_err(|e| format!("{:?}", e))?;
+        let header = serde_json::from_slice(&header)
+            .map_err(|e| format!("Invalid filter header: {}", e))?;
+
+        let config = bincode::config::standard()
+            .with_fixed_int_encoding()
+            .with_limit::<MAX_FILTER_SIZE>();
+        let filter = bincode::decode_from_std_read(&mut reader, config)
+            .map_err(|e| format!("Error reading filter: {}", e))?;
+
+        Ok( Self { header, filter })
+    }
+
+    /// Read a filter from a file
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let fh = File::open(path)
+            .map_err(|e| format!("Could not open '{}'. {:?}", path.to_string_lossy(), e))?;
+        Self::read_from(BufReader::new(fh))
+    }
+}
@@ -1,25 +1,85 @@
 use std::path::{Path, PathBuf};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::sync::{Arc, RwLock};
 use git2::build::RepoBuilder;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use tantivy::schema::*;
-use tantivy::{Index, Document, IndexWriter, IndexReader};
-use tantivy::{LeasedItem, Searcher};
-use tantivy::collector::TopDocs;
-use tantivy::query::QueryParser;
+use tantivy::{Index, Document, IndexWriter, IndexReader, ReloadPolicy};
+use tantivy::{DocAddress, LeasedItem, Searcher, SegmentId};
+use tantivy::collector::{Count, DocSetCollector, TopDocs};
+use std::ops::Bound;
+use tantivy::query::{
+    AllQuery, BooleanQuery, Occur, Query, QueryParser, RangeQuery, RegexQuery, TermQuery
+};
 use tantivy::error::TantivyError;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
-use crate::error::VanillaError;
+use crate::error::{ErrorKind, VanillaError};
+use crate::similar::{SimilarName, SimilarNameIndex};
 use crate::tokenizer::RawLowerTokenizer;
-use crate::vanilla::{WindowsFileList, WinFileListIterator, get_system_info_files};
+use crate::winpath::{Volume, WindowsPath};
+use crate::vanilla::{
+    VanillaFileRecord, WindowsFileList, WinFileListIterator,
+    get_system_info_files
+};
 
-const FIELDS_STRING: &[&'static str] = &["DirectoryName", "Name", "MD5", "SHA256"];
+const FIELDS_STRING: &[&str] = &[
+    "DirectoryName", "Name", "FullName", "MD5", "SHA256",
+    "OsName", "OsVersion", "SystemType", "SystemLocale", "Hotfixes",
+    "Edition", "Architecture", "Release", "FullBuild"
+];
+#[allow(clippy::redundant_static_lifetimes)]
 const FIELDS_EXCLUDE: &[&'static str] = &["Attributes", "Sddl"];
+const FIELDS_U64: &[&str] = &["Length", "OsBuild"];
+const FIELDS_DATE: &[&str] = &["CreationTimeUtc", "LastAccessTimeUtc", "LastWriteTimeUtc"];
 
+/// Fields that identify the data set a document came from
+const FIELD_DATA_SET_PATH: &str = "DataSetPath";
+const FIELD_DATA_SET_HASH: &str = "DataSetHash";
+
+/// The max number of documents looked at for a full name
+const FULL_NAME_HIT_LIMIT: usize = 10_000;
+/// The max `offset + limit` of a search. The collector allocates a heap of
+/// this size, so it must be bounded.
+pub const MAX_SEARCH_WINDOW: usize = 10_000;
+
+#[allow(unused_parens)]
 type SearchQuery = (LeasedItem<Searcher>, Box<(dyn tantivy::query::Query + 'static)>);
+type QueryClause = (Occur, Box<dyn Query>);
+
+
+/// Get the index field for a hash based off of its length.
+pub fn get_hash_field(value: &str) -> Result<&'static str, String> {
+    match value.len() {
+        32 => Ok("MD5"),
+        64 => Ok("SHA256"),
+        len => Err(format!("Unhandled hash type with length: {}", len))
+    }
+}
+
+
+/// Convert a glob into an anchored regex for the lowercase terms of the index.
+/// `*` matches any characters and `?` a single character, everything else is
+/// matched as is.
+pub fn glob_to_regex(glob: &str) -> String {
+    let mut pattern = String::with_capacity(glob.len() * 2);
+    for c in glob.to_lowercase().chars() {
+        match c {
+            '*' => pattern.push_str(".*"),
+            '?' => pattern.push('.'),
+            '\\' | '.' | '+' | '(' | ')' | '|' | '[' | ']' | '{' | '}' | '^' | '$' => {
+                pattern.push('\\');
+                pattern.push(c);
+            },
+            c => pattern.push(c)
+        }
+    }
+    pattern
+}
 
 
 /// Clone the VanillaReference folder
+#[allow(clippy::needless_borrow)]
 pub fn clone_vanilla_reference_repo(
     destination: impl AsRef<Path>
 ) -> Result<(), VanillaError> {
@@ -53,6 +113,7 @@ fn get_index_fields(path: impl AsRef<Path>) -> HashSet<String> {
 
 /// Create a schema based of the fields found in the Vanilla reference
 /// file lists. This can be used for generating an Index.
+#[allow(clippy::needless_borrow, clippy::needless_borrows_for_generic_args)]
 pub fn generate_schema_from_vanilla(
     path: impl AsRef<Path>
 ) -> Result<Schema, String> {
@@ -65,7 +126,18 @@ pub fn generate_schema_from_vanilla(
     let mut schema_builder = Schema::builder();
 
     for field in &fields {
-        if FIELDS_EXCLUDE.contains(&field.as_str()) {
+        // The data set identity is added below
+        if FIELDS_EXCLUDE.contains(&field.as_str()) || field == FIELD_DATA_SET_PATH {
+            continue
+        }
+
+        // Numeric and date fields can be used in range queries
+        if FIELDS_U64.contains(&field.as_str()) {
+            schema_builder.add_u64_field(field, INDEXED | STORED | FAST);
+            continue
+        }
+        if FIELDS_DATE.contains(&field.as_str()) {
+            schema_builder.add_date_field(field, INDEXED | STORED | FAST);
             continue
         }
 
@@ -89,24 +161,127 @@ pub fn generate_schema_from_vanilla(
         };
     }
 
+    // Data set identity used for incremental indexing
+    schema_builder.add_text_field(FIELD_DATA_SET_PATH, STRING | STORED);
+    schema_builder.add_text_field(FIELD_DATA_SET_HASH, STRING | STORED);
+
     Ok(schema_builder.build())
 }
 
 
+/// Add a Windows Info value to a document based on the field's type.
+fn add_info_value(doc: &mut Document, field: Field, field_entry: &FieldEntry, value: &Value) {
+    match (field_entry.field_type(), value) {
+        (FieldType::U64(_), Value::Number(n)) => {
+            if let Some(n) = n.as_u64() {
+                doc.add_u64(field, n);
+            }
+        },
+        (FieldType::Str(_), Value::String(s)) => {
+            doc.add_text(field, s);
+        },
+        // Multi-valued fields such as Hotfixes
+        (FieldType::Str(_), Value::Array(values)) => {
+            for s in values.iter().filter_map(|v| v.as_str()) {
+                doc.add_text(field, s);
+            }
+        },
+        _ => {}
+    }
+}
+
+
+/// Convert a VanillaFileRecord and the Windows Info of its file list into a
+/// Document for the given schema.
+fn record_to_document(
+    record: &VanillaFileRecord,
+    win_info: &Value,
+    schema: &Schema
+) -> Document {
+    let mut doc = Document::new();
+
+    for (field, field_entry) in schema.fields() {
+        let field_name = field_entry.name();
+
+        // Skip indexing excluded fields
+        if FIELDS_EXCLUDE.contains(&field_name) {
+            continue
+        }
+
+        match field_name {
+            // index conversions (volume relative paths)
+            "DirectoryName" => doc.add_text(field, record.directory_name.relative_path()),
+            "FullName" => doc.add_text(field, record.full_name.relative_path()),
+            "Name" => doc.add_text(field, &record.name),
+            "Length" => doc.add_u64(field, record.length),
+            "CreationTimeUtc" => {
+                if let Some(dt) = record.creation_time {
+                    doc.add_date(field, dt);
+                }
+            },
+            "LastAccessTimeUtc" => {
+                if let Some(dt) = record.last_access_time {
+                    doc.add_date(field, dt);
+                }
+            },
+            "LastWriteTimeUtc" => {
+                if let Some(dt) = record.last_write_time {
+                    doc.add_date(field, dt);
+                }
+            },
+            "MD5" => {
+                if let Some(md5) = &record.md5 {
+                    doc.add_text(field, md5);
+                }
+            },
+            "SHA256" => {
+                if let Some(sha256) = &record.sha256 {
+                    doc.add_text(field, sha256);
+                }
+            },
+            _ => {
+                if let Some(value) = win_info.get(field_name) {
+                    add_info_value(&mut doc, field, field_entry, value);
+                }
+            }
+        }
+    }
+
+    doc
+}
+
+
+/// A data set (file list) to be indexed along with its identity.
+struct DataSetAction {
+    /// The (0 based) number of the data set
+    number: usize,
+    location: PathBuf,
+    file_list: WindowsFileList,
+    /// The data set folder relative to the vanilla path
+    path: String,
+    /// The content hash of the data set files
+    hash: String
+}
+
+
 /// Index a file list
+#[allow(clippy::redundant_closure)]
 fn index_file_list(
     set_count: usize,
-    tuple: &(usize, PathBuf, WindowsFileList),
+    action: &DataSetAction,
     index_writer: &IndexWriter,
     schema: &Schema
 ) -> Result<(), VanillaError> {
-    let (i, location, file_list) = tuple;
-
-    let i = i + 1;
+    let location = &action.location;
+    let i = action.number + 1;
             
     // Get the record iterator from the file list
-    let record_iter = file_list.into_iter()
+    let record_iter = action.file_list.into_record_iter()
         .map_err(|e| VanillaError::from_message(e))?;
+    let mut win_info = json!(&record_iter.win_info);
+    // Sets the DataSetPath along with the other data set values
+    action.file_list.data_set().add_to_value(&mut win_info);
+    win_info[FIELD_DATA_SET_HASH] = json!(action.hash);
 
     info!(
         "[starting {}/{}] Indexing path: {}",
@@ -114,39 +289,15 @@ fn index_file_list(
         location.to_string_lossy()
     );
     // Iterate each record
-    for mut record in record_iter {
-        let mut doc = Document::new();
-        // index conversions (all in lowercase)
-        if let Some(n) = record["DirectoryName"].as_str() {
-            record["DirectoryName"] = json!(n[3..]);
-        }
-        if let Some(n) = record["FullName"].as_str() {
-            record["FullName"] = json!(n[3..]);
-        }
-        if let Some(n) = record["MD5"].as_str() {
-            record["MD5"] = json!(n);
-        }
-        if let Some(n) = record["SHA256"].as_str() {
-            record["SHA256"] = json!(n);
-        }
-
-        for (field, field_entry) in schema.fields() {
-            let field_name = field_entry.name();
-
-            // Skip indexing excluded fields
-            if FIELDS_EXCLUDE.contains(&field_name) {
-                continue
-            }
-            if let Some(field_value) = record.get(field_name) {
-                if let Some(fv_str) = field_value.as_str() {
-                    doc.add_text(
-                        field,
-                        fv_str
-                    );
-                }
+    for record in record_iter {
+        let record = match record {
+            Ok(r) => r,
+            Err(e) => {
+                error!("Error reading record in {}: {}", location.to_string_lossy(), e);
+                continue;
             }
-        }
-
+        };
+        let doc = record_to_document(&record, &win_info, schema);
         if let Err(err) = index_writer.add_document(doc) {
             error!("Error adding document: {:?}", err);
         };
@@ -162,45 +313,257 @@ fn index_file_list(
 }
 
 
+/// A hash that was seen at a full name and the OS versions it was seen in.
+#[derive(Debug, Clone, Serialize)]
+pub struct SeenHash {
+    #[serde(rename = "MD5")]
+    pub md5: Option<String>,
+    #[serde(rename = "SHA256")]
+    pub sha256: Option<String>,
+    #[serde(rename = "OsVersions")]
+    pub os_versions: Vec<String>,
+}
+
+
+/// The result of checking a hash against a full name.
+#[derive(Debug, Clone, Serialize)]
+pub struct FullNameHashCheck {
+    /// The full name has been seen
+    #[serde(rename = "KnownPath")]
+    pub known_path: bool,
+    /// The hash has been seen at the full name
+    #[serde(rename = "KnownHash")]
+    pub known_hash: bool,
+    /// The hashes seen at the full name, only set when the hash is not known
+    #[serde(rename = "SeenHashes")]
+    pub seen_hashes: Vec<SeenHash>,
+    /// The full name has more documents than were looked at, so SeenHashes can
+    /// be missing some
+    #[serde(rename = "SeenHashesTruncated")]
+    pub seen_hashes_truncated: bool,
+}
+
+
+/// A page of search hits.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResults {
+    /// The number of documents that matched
+    #[serde(rename = "Total")]
+    pub total: usize,
+    #[serde(rename = "Offset")]
+    pub offset: usize,
+    #[serde(rename = "Limit")]
+    pub limit: usize,
+    #[serde(rename = "Hits")]
+    pub hits: Vec<Value>,
+}
+
+
+/// A file as it was seen in one data set.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildHistoryEntry {
+    #[serde(rename = "DataSetPath")]
+    pub data_set_path: Option<String>,
+    #[serde(rename = "OsName")]
+    pub os_name: Option<String>,
+    #[serde(rename = "OsVersion")]
+    pub os_version: Option<String>,
+    #[serde(rename = "OsBuild")]
+    pub os_build: Option<u64>,
+    #[serde(rename = "FullBuild")]
+    pub full_build: Option<String>,
+    #[serde(rename = "Edition")]
+    pub edition: Option<String>,
+    #[serde(rename = "Architecture")]
+    pub architecture: Option<String>,
+    /// Without the drive letter
+    #[serde(rename = "FullName")]
+    pub full_name: Option<String>,
+    #[serde(rename = "Length")]
+    pub length: Option<u64>,
+    #[serde(rename = "MD5")]
+    pub md5: Option<String>,
+    #[serde(rename = "SHA256")]
+    pub sha256: Option<String>,
+}
+impl BuildHistoryEntry {
+    /// Create an entry from a hit (named document)
+    fn from_hit(hit: &Value) -> Self {
+        let get_str = |field: &str| get_named_doc_strs(hit, field)
+            .first()
+            .map(|v| v.to_string());
+        let get_u64 = |field: &str| hit.get(field)
+            .and_then(|v| v.get(0))
+            .and_then(|v| v.as_u64());
+
+        Self {
+            data_set_path: get_str(FIELD_DATA_SET_PATH),
+            os_name: get_str("OsName"),
+            os_version: get_str("OsVersion"),
+            os_build: get_u64("OsBuild"),
+            full_build: get_str("FullBuild"),
+            edition: get_str("Edition"),
+            architecture: get_str("Architecture"),
+            full_name: get_str("FullName"),
+            length: get_u64("Length"),
+            md5: get_str("MD5"),
+            sha256: get_str("SHA256"),
+        }
+    }
+
+    /// The build and UBR (e.g. `19042.631`) used to order entries
+    fn get_build_key(&self) -> (u64, u64) {
+        let ubr = self.full_build.as_deref()
+            .and_then(|b| b.split_once('.'))
+            .and_then(|(_, ubr)| ubr.parse::<u64>().ok())
+            .unwrap_or_default();
+        (self.os_build.unwrap_or_default(), ubr)
+    }
+
+    /// The Edition and Architecture of the entry
+    fn get_variant(&self) -> (&Option<String>, &Option<String>) {
+        (&self.edition, &self.architecture)
+    }
+
+    /// The FullBuild, or else the OsBuild, of the entry
+    pub fn get_build_label(&self) -> Option<String> {
+        self.full_build.clone()
+            .or_else(|| self.os_build.map(|b| b.to_string()))
+    }
+}
+
+
+/// Every data set a file was seen in, ordered by build.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildHistory {
+    /// The build (see `BuildHistoryEntry::get_build_label`) of the first entry
+    #[serde(rename = "FirstSeen")]
+    pub first_seen: Option<String>,
+    /// The build of the last entry
+    #[serde(rename = "LastSeen")]
+    pub last_seen: Option<String>,
+    #[serde(rename = "Builds")]
+    pub builds: Vec<BuildHistoryEntry>,
+    /// More documents matched than were looked at, so `Builds` is incomplete
+    /// and the gaps are not reported
+    #[serde(rename = "Truncated")]
+    pub truncated: bool,
+    /// The indexed builds between the first and last seen builds that do not
+    /// have the file. Only data sets of the same editions and architectures as
+    /// the entries are compared.
+    #[serde(rename = "MissingBuilds")]
+    pub missing_builds: Vec<String>,
+    /// Newer builds (of the same editions and architectures) than the last
+    /// seen build are indexed, so the file was removed (or moved)
+    #[serde(rename = "Removed")]
+    pub removed: bool,
+}
+
+
+/// Optional filters that restrict lookups to documents from comparable OS builds.
+/// All the set filters must match.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LookupFilter {
+    /// Exact (case insensitive) OsName (e.g. `Microsoft Windows 10 Pro`)
+    pub os_name: Option<String>,
+    /// Exact (case insensitive) OsVersion (e.g. `10.0.18363 N/A Build 18363`)
+    pub os_version: Option<String>,
+    /// The lowest OsBuild to include
+    pub min_build: Option<u64>,
+    /// The highest OsBuild to include
+    pub max_build: Option<u64>,
+    /// Exact (case sensitive) DataSetPath (e.g. `Windows10/20H2/W10_20H2_Pro_19042.631`)
+    pub data_set_path: Option<String>,
+}
+impl LookupFilter {
+    /// Check if no filters are set
+    pub fn is_empty(&self) -> bool {
+        self.os_name.is_none() &&
+        self.os_version.is_none() &&
+        self.min_build.is_none() &&
+        self.max_build.is_none() &&
+        self.data_set_path.is_none()
+    }
+
+    /// Check that the build range is valid
+    pub fn validate(&self) -> Result<(), VanillaError> {
+        if let (Some(min), Some(max)) = (self.min_build, self.max_build) {
+            if min > max {
+                return Err(VanillaError::invalid_input(format!(
+                    "min_build {} is greater than max_build {}", min, max
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+
+/// Get the values of a field from a named document as strings
+fn get_named_doc_strs<'a>(hit: &'a Value, field: &str) -> Vec<&'a str> {
+    hit.get(field)
+        .and_then(|v| v.as_array())
+        .map(|values| values.iter().filter_map(|v| v.as_str()).collect())
+        .unwrap_or_default()
+}
+
+
+/// Check that a page of hits (`offset + limit`) is within `MAX_SEARCH_WINDOW`
+pub fn check_search_window(limit: usize, offset: usize) -> Result<(), VanillaError> {
+    match offset.checked_add(limit) {
+        Some(window) if window <= MAX_SEARCH_WINDOW => Ok(()),
+        _ => Err(VanillaError::invalid_input(
+            format!("offset + limit is greater than {}", MAX_SEARCH_WINDOW)
+        ))
+    }
+}
+
+
 /// Handle Index reading operations such as queries.
 pub struct WindowsRefIndexReader {
     /// The IndexReader
-    index_reader: IndexReader
+    index_reader: IndexReader,
+    /// The known names for similar name lookups and the segments they were
+    /// read from
+    similar_names: RwLock<Option<(Vec<SegmentId>, Arc<SimilarNameIndex>)>>
 }
 impl WindowsRefIndexReader {
-    /// Get the Searcher and Query struct for a given query string
-    fn get_query(
-        &self,
-        query: &str
-    ) -> Result<SearchQuery, VanillaError> {
-        let searcher = self.index_reader.searcher();
+    /// Get a QueryParser for the index of a searcher. All fields are used as
+    /// default fields.
+    fn get_query_parser(searcher: &Searcher) -> QueryParser {
         let schema = searcher.schema();
         let schema_vec = schema.fields()
             .map(|v|v.0)
             .collect();
 
-        let index = searcher.index();
-
-        let query_parser = QueryParser::for_index(
-            index, 
+        QueryParser::for_index(
+            searcher.index(), 
             schema_vec
-        );
+        )
+    }
+
+    /// Get the Searcher and Query struct for a given query string
+    fn get_query(
+        &self,
+        query: &str
+    ) -> Result<SearchQuery, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let query_parser = Self::get_query_parser(&searcher);
 
         let query = query_parser.parse_query(query)
-            .map_err(|e|VanillaError::from_message(format!("{:?}", e)))?;
+            .map_err(|e|VanillaError::invalid_query(format!("{:?}", e)))?;
         
         Ok((searcher, query))
     }
 
-    /// Get a hits for a given query
-    pub fn get_query_hits(
-        &self, 
-        query: &str,
+    /// Get the named documents for the top hits of a query
+    fn search_named_docs(
+        searcher: &Searcher,
+        query: &dyn Query,
         limit: usize
-    ) -> Result<Vec<Value>, VanillaError>{
-        let (searcher, query) = self.get_query(query)?;
+    ) -> Result<Vec<Value>, VanillaError> {
         let docs = searcher.search(
-            &query,
+            query,
             &TopDocs::with_limit(limit)
         )?;
         let schema = searcher.schema();
@@ -215,6 +578,502 @@ impl WindowsRefIndexReader {
 
         Ok(records)
     }
+
+    /// Get a hits for a given query
+    pub fn get_query_hits(
+        &self, 
+        query: &str,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError>{
+        let (searcher, query) = self.get_query(query)?;
+        Self::search_named_docs(&searcher, query.as_ref(), limit)
+    }
+
+    /// Get a field of the schema by its name
+    fn get_field(schema: &Schema, name: &str) -> Result<Field, VanillaError> {
+        schema.get_field(name)
+            .ok_or_else(|| VanillaError::new(
+                ErrorKind::Index,
+                format!("Field {} is not in the index schema", name)
+            ))
+    }
+
+    /// Create a TermQuery for an exact (case insensitive) value of a rawlower field.
+    /// The value is not parsed, so quotes, colons, etc. are matched as is.
+    fn get_term_query(
+        schema: &Schema,
+        field_name: &str,
+        value: &str
+    ) -> Result<Box<dyn Query>, VanillaError> {
+        let field = Self::get_field(schema, field_name)?;
+        let term = Term::from_field_text(field, &value.to_lowercase());
+        Ok(Box::new(TermQuery::new(term, IndexRecordOption::Basic)))
+    }
+
+    /// Get the query clauses for the set filters of a LookupFilter
+    fn get_filter_clauses(
+        schema: &Schema,
+        filter: &LookupFilter
+    ) -> Result<Vec<QueryClause>, VanillaError> {
+        let mut clauses: Vec<QueryClause> = Vec::new();
+
+        if let Some(os_name) = &filter.os_name {
+            clauses.push((Occur::Must, Self::get_term_query(schema, "OsName", os_name)?));
+        }
+        if let Some(os_version) = &filter.os_version {
+            clauses.push((Occur::Must, Self::get_term_query(schema, "OsVersion", os_version)?));
+        }
+
+        if filter.min_build.is_some() || filter.max_build.is_some() {
+            filter.validate()?;
+
+            let field = Self::get_field(schema, "OsBuild")?;
+            let lower = filter.min_build.map_or(Bound::Unbounded, Bound::Included);
+            let upper = filter.max_build.map_or(Bound::Unbounded, Bound::Included);
+            clauses.push((Occur::Must, Box::new(RangeQuery::new_u64_bounds(field, lower, upper))));
+        }
+
+        // DataSetPath is not lowercased, so the term is used as is
+        if let Some(data_set_path) = &filter.data_set_path {
+            let field = Self::get_field(schema, FIELD_DATA_SET_PATH)?;
+            let term = Term::from_field_text(field, data_set_path);
+            clauses.push((Occur::Must, Box::new(TermQuery::new(term, IndexRecordOption::Basic))));
+        }
+
+        Ok(clauses)
+    }
+
+    /// Create a query that requires all of the (field, value) pairs and the
+    /// filters to match.
+    fn get_terms_query(
+        schema: &Schema,
+        terms: &[(&str, &str)],
+        filter: &LookupFilter
+    ) -> Result<Box<dyn Query>, VanillaError> {
+        let mut clauses = Vec::with_capacity(terms.len());
+        for (field_name, value) in terms {
+            clauses.push((Occur::Must, Self::get_term_query(schema, field_name, value)?));
+        }
+        clauses.extend(Self::get_filter_clauses(schema, filter)?);
+        Ok(Box::new(BooleanQuery::new(clauses)))
+    }
+
+    /// Get the hits where all of the (field, value) pairs and the filters match
+    fn lookup_terms(
+        &self,
+        terms: &[(&str, &str)],
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let query = Self::get_terms_query(searcher.schema(), terms, filter)?;
+        Self::search_named_docs(&searcher, query.as_ref(), limit)
+    }
+
+    /// Count the documents where all of the (field, value) pairs and the filters
+    /// match. No stored documents are loaded.
+    fn count_terms(
+        &self,
+        terms: &[(&str, &str)],
+        filter: &LookupFilter
+    ) -> Result<usize, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let query = Self::get_terms_query(searcher.schema(), terms, filter)?;
+        Ok(searcher.search(query.as_ref(), &Count)?)
+    }
+
+    /// Count the documents of a file name (e.g. `cmd.exe`)
+    pub fn count_by_name(
+        &self,
+        name: &str,
+        filter: &LookupFilter
+    ) -> Result<usize, VanillaError> {
+        self.count_terms(&[("Name", name)], filter)
+    }
+
+    /// Count the documents of a file name in a directory
+    pub fn count_by_name_in_directory(
+        &self,
+        name: &str,
+        directory: &str,
+        filter: &LookupFilter
+    ) -> Result<usize, VanillaError> {
+        self.count_terms(&[("Name", name), ("DirectoryName", directory)], filter)
+    }
+
+    /// Call `f` with every document (as a named document) that matches the
+    /// filters (all documents when no filter is set). The documents are not
+    /// scored and are read one at a time in index order. `f` must not call other
+    /// reader methods, since the searcher is held while it runs.
+    pub fn for_each_doc(
+        &self,
+        filter: &LookupFilter,
+        mut f: impl FnMut(Value)
+    ) -> Result<(), VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let query: Box<dyn Query> = if filter.is_empty() {
+            Box::new(AllQuery)
+        } else {
+            Self::get_terms_query(searcher.schema(), &[], filter)?
+        };
+
+        let mut doc_addresses: Vec<DocAddress> = searcher.search(query.as_ref(), &DocSetCollector)?
+            .into_iter()
+            .collect();
+        doc_addresses.sort_unstable();
+
+        let schema = searcher.schema();
+        for doc_address in doc_addresses {
+            let doc = searcher.doc(doc_address)?;
+            f(json!(schema.to_named_doc(&doc)));
+        }
+
+        Ok(())
+    }
+
+    /// Get the hits for a file name (e.g. `cmd.exe`)
+    pub fn lookup_by_name(
+        &self,
+        name: &str,
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        self.lookup_terms(&[("Name", name)], filter, limit)
+    }
+
+    /// Get the hits for a directory without the drive letter (e.g. `windows\system32`)
+    pub fn lookup_by_directory(
+        &self,
+        directory: &str,
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        self.lookup_terms(&[("DirectoryName", directory)], filter, limit)
+    }
+
+    /// Get the hits for a file name in a directory
+    pub fn lookup_by_name_in_directory(
+        &self,
+        name: &str,
+        directory: &str,
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        self.lookup_terms(&[("Name", name), ("DirectoryName", directory)], filter, limit)
+    }
+
+    /// Get the hits for a full name without the drive letter (e.g. `windows\system32\cmd.exe`)
+    pub fn lookup_by_full_name(
+        &self,
+        full_name: &str,
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        self.lookup_terms(&[("FullName", full_name)], filter, limit)
+    }
+
+    /// Get the hits for a MD5 or SHA256 hash
+    pub fn lookup_by_hash(
+        &self,
+        hash: &str,
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Value>, VanillaError> {
+        let field = get_hash_field(hash)
+            .map_err(VanillaError::invalid_input)?;
+        self.lookup_terms(&[(field, hash)], filter, limit)
+    }
+
+    /// Get the hits for multiple MD5/SHA256 hashes using a single searcher. The
+    /// returned Vec has the hits of each hash in the same order as `hashes`.
+    pub fn lookup_by_hashes(
+        &self,
+        hashes: &[String],
+        filter: &LookupFilter,
+        limit: usize
+    ) -> Result<Vec<Vec<Value>>, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let schema = searcher.schema();
+
+        let mut results = Vec::with_capacity(hashes.len());
+        for hash in hashes {
+            let field = get_hash_field(hash)
+                .map_err(VanillaError::invalid_input)?;
+            let query = Self::get_terms_query(schema, &[(field, hash)], filter)?;
+            results.push(Self::search_named_docs(&searcher, query.as_ref(), limit)?);
+        }
+
+        Ok(results)
+    }
+
+    /// Check if a MD5/SHA256 hash has ever been seen at a full name (without the
+    /// drive letter, e.g. `windows\system32\cmd.exe`) across all indexed builds.
+    /// When it has not, the hashes seen at the full name are returned along with
+    /// the OS versions they were seen in. Only the builds matching `filter` are
+    /// checked.
+    pub fn get_full_name_hash_check(
+        &self,
+        full_name: &str,
+        hash: &str,
+        filter: &LookupFilter
+    ) -> Result<FullNameHashCheck, VanillaError> {
+        let hash_field = get_hash_field(hash)
+            .map_err(VanillaError::invalid_input)?;
+
+        let (directory, name) = full_name.rsplit_once('\\')
+            .unwrap_or(("", full_name));
+        let path_terms = [("Name", name), ("DirectoryName", directory)];
+
+        // Both are term queries, no documents are loaded
+        let count = self.count_terms(&path_terms, filter)?;
+        let known_hash = count > 0 && self.count_terms(
+            &[("Name", name), ("DirectoryName", directory), (hash_field, hash)],
+            filter
+        )? > 0;
+
+        // The seen hashes are only needed to explain a hash that is not known
+        let hits = if count > 0 && !known_hash {
+            self.lookup_terms(&path_terms, filter, FULL_NAME_HIT_LIMIT)?
+        } else {
+            Vec::new()
+        };
+
+        // (MD5, SHA256) -> OS versions
+        let mut hashes: BTreeMap<(Option<String>, Option<String>), BTreeSet<String>> = BTreeMap::new();
+        for hit in &hits {
+            let md5 = get_named_doc_strs(hit, "MD5").first()
+                .map(|v| v.to_string());
+            let sha256 = get_named_doc_strs(hit, "SHA256").first()
+                .map(|v| v.to_string());
+
+            hashes.entry((md5, sha256))
+                .or_default()
+                .extend(get_named_doc_strs(hit, "OsVersion").into_iter().map(|v| v.to_string()));
+        }
+
+        let seen_hashes: Vec<SeenHash> = hashes.into_iter()
+            .map(|((md5, sha256), os_versions)| SeenHash {
+                md5,
+                sha256,
+                os_versions: os_versions.into_iter().collect()
+            })
+            .collect();
+
+        Ok( FullNameHashCheck {
+            known_path: count > 0,
+            known_hash,
+            seen_hashes,
+            seen_hashes_truncated: !known_hash && count > FULL_NAME_HIT_LIMIT
+        })
+    }
+
+    /// Search file names or full names with a glob such as `*svc*.dll` or
+    /// `windows\system32\drivers\*.sys` (case insensitive). Globs with a `\` or
+    /// `/` are matched against the full name without the drive letter, others
+    /// against the file name. `limit` and `offset` page through the hits and
+    /// together can not be more than `MAX_SEARCH_WINDOW`.
+    pub fn search_glob(
+        &self,
+        glob: &str,
+        filter: &LookupFilter,
+        limit: usize,
+        offset: usize
+    ) -> Result<SearchResults, VanillaError> {
+        if limit == 0 {
+            return Err(VanillaError::invalid_input("limit must be greater than 0".to_string()));
+        }
+        check_search_window(limit, offset)?;
+
+        let is_path = glob.contains(['\\', '/']);
+        let glob = WindowsPath::new(glob).relative_path().to_string();
+        if glob.is_empty() {
+            return Err(VanillaError::invalid_input("The glob is empty".to_string()));
+        }
+
+        let searcher = self.index_reader.searcher();
+        let schema = searcher.schema();
+        let field = Self::get_field(schema, if is_path { "FullName" } else { "Name" })?;
+        let regex_query = RegexQuery::from_pattern(&glob_to_regex(&glob), field)
+            .map_err(|e| VanillaError::invalid_query(format!("{:?}", e)))?;
+
+        let mut clauses: Vec<QueryClause> = vec![(Occur::Must, Box::new(regex_query))];
+        clauses.extend(Self::get_filter_clauses(schema, filter)?);
+        let query = BooleanQuery::new(clauses);
+
+        let (docs, total) = searcher.search(
+            &query,
+            &(TopDocs::with_limit(limit).and_offset(offset), Count)
+        )?;
+
+        let mut hits = Vec::with_capacity(docs.len());
+        for (_score, doc_address) in docs {
+            let retrieved_doc = searcher.doc(doc_address)?;
+            hits.push(json!(&schema.to_named_doc(&retrieved_doc)));
+        }
+
+        Ok( SearchResults { total, offset, limit, hits } )
+    }
+
+    /// Get the SimilarNameIndex of the Name terms. It is built the first time
+    /// it is needed and again only when the segments of the index change.
+    fn get_similar_name_index(&self) -> Result<Arc<SimilarNameIndex>, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let segment_ids: Vec<SegmentId> = searcher.segment_readers()
+            .iter()
+            .map(|s| s.segment_id())
+            .collect();
+
+        if let Some((ids, similar_names)) = self.similar_names.read()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_ref()
+        {
+            if *ids == segment_ids {
+                return Ok(similar_names.clone());
+            }
+        }
+
+        let field = Self::get_field(searcher.schema(), "Name")?;
+        let mut names = Vec::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                names.push(String::from_utf8_lossy(stream.key()).to_string());
+            }
+        }
+        let similar_names = Arc::new(SimilarNameIndex::from_names(names));
+
+        *self.similar_names.write().unwrap_or_else(|e| e.into_inner()) =
+            Some((segment_ids, similar_names.clone()));
+        Ok(similar_names)
+    }
+
+    /// Get the known names that look like a name (e.g. `svchost.exe` for
+    /// `svch0st.exe`), best first. The names of the index are compared by edit
+    /// distance after confusable normalization (see `similar::SimilarNameIndex`).
+    pub fn get_similar_names(
+        &self,
+        name: &str,
+        limit: usize
+    ) -> Result<Vec<SimilarName>, VanillaError> {
+        let mut similar = self.get_similar_name_index()?
+            .get_similar_names(name, limit);
+
+        // The terms are lowercase, so the name is taken from a document
+        let filter = LookupFilter::default();
+        for s in similar.iter_mut() {
+            let hits = self.lookup_by_name(&s.name, &filter, 1)?;
+            if let Some(known) = hits.first().and_then(|h| get_named_doc_strs(h, "Name").first().copied()) {
+                s.name = known.to_owned();
+            }
+        }
+
+        Ok(similar)
+    }
+
+    /// Get every data set a file exists in, ordered by build (and UBR), with the
+    /// hashes and size of each. `path` is a full path (e.g.
+    /// `C:\Windows\System32\cmd.exe` or `C:\bootmgr`) or, without a volume or
+    /// directory, a file name in any directory. Only the builds matching `filter`
+    /// are checked, including for the builds the file is missing from.
+    pub fn get_build_history(
+        &self,
+        path: &WindowsPath,
+        filter: &LookupFilter
+    ) -> Result<BuildHistory, VanillaError> {
+        let terms = if *path.volume() == Volume::None && path.parent().is_empty() {
+            [("Name", path.file_name())]
+        } else {
+            [("FullName", path.relative_path())]
+        };
+        let count = self.count_terms(&terms, filter)?;
+        let hits = self.lookup_terms(&terms, filter, FULL_NAME_HIT_LIMIT)?;
+
+        let mut builds: Vec<BuildHistoryEntry> = hits.iter()
+            .map(BuildHistoryEntry::from_hit)
+            .collect();
+        builds.sort_by(|a, b| a.get_build_key().cmp(&b.get_build_key())
+            .then_with(|| a.data_set_path.cmp(&b.data_set_path))
+            .then_with(|| a.full_name.cmp(&b.full_name)));
+
+        let truncated = count > FULL_NAME_HIT_LIMIT;
+        let mut missing_builds = Vec::new();
+        let mut removed = false;
+        if let (false, Some(first), Some(last)) = (truncated, builds.first(), builds.last()) {
+            let (first, last) = (first.get_build_key(), last.get_build_key());
+            let seen: HashSet<(u64, u64)> = builds.iter()
+                .map(|b| b.get_build_key())
+                .collect();
+            // Only data sets of the editions and architectures the file is in are
+            // compared, a Server or ARM only file is not removed by a Pro x64 build
+            let variants: HashSet<(&Option<String>, &Option<String>)> = builds.iter()
+                .map(|b| b.get_variant())
+                .collect();
+
+            // A build is only missing if none of its data sets (editions) has the file
+            let mut checked = HashSet::new();
+            let data_sets = self.get_data_set_builds(filter)?;
+            for data_set in data_sets.iter().filter(|d| variants.contains(&d.get_variant())) {
+                let key = data_set.get_build_key();
+                if key > last {
+                    removed = true;
+                } else if key > first && !seen.contains(&key) && checked.insert(key) {
+                    missing_builds.extend(data_set.get_build_label());
+                }
+            }
+        }
+
+        Ok( BuildHistory {
+            first_seen: builds.first().and_then(|b| b.get_build_label()),
+            last_seen: builds.last().and_then(|b| b.get_build_label()),
+            builds,
+            truncated,
+            missing_builds,
+            removed
+        })
+    }
+
+    /// Get an entry (without the file fields) for every indexed data set that
+    /// matches the filters, ordered by build.
+    fn get_data_set_builds(
+        &self,
+        filter: &LookupFilter
+    ) -> Result<Vec<BuildHistoryEntry>, VanillaError> {
+        let searcher = self.index_reader.searcher();
+        let path_field = Self::get_field(searcher.schema(), FIELD_DATA_SET_PATH)?;
+
+        let mut paths = BTreeSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(path_field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                paths.insert(String::from_utf8_lossy(stream.key()).to_string());
+            }
+        }
+
+        // The term dictionary still holds the terms of deleted documents, so a
+        // data set is only used if it has a (matching) live document.
+        let mut data_sets = Vec::new();
+        for path in paths {
+            let filter = LookupFilter {
+                data_set_path: Some(path),
+                ..filter.clone()
+            };
+            let query = Self::get_terms_query(searcher.schema(), &[], &filter)?;
+            let hits = Self::search_named_docs(&searcher, query.as_ref(), 1)?;
+            if let Some(hit) = hits.first() {
+                let mut data_set = BuildHistoryEntry::from_hit(hit);
+                data_set.full_name = None;
+                data_set.length = None;
+                data_set.md5 = None;
+                data_set.sha256 = None;
+                data_sets.push(data_set);
+            }
+        }
+        data_sets.sort_by_key(|d| d.get_build_key());
+
+        Ok(data_sets)
+    }
 }
 impl TryFrom<Index> for WindowsRefIndexReader {
     type Error = TantivyError;
@@ -224,11 +1083,24 @@ impl TryFrom<Index> for WindowsRefIndexReader {
         index.tokenizers()
             .register("rawlower", RawLowerTokenizer);
         let index_reader = index.reader()?;
-        Ok(WindowsRefIndexReader{index_reader})
+        Ok(WindowsRefIndexReader{
+            index_reader,
+            similar_names: RwLock::new(None)
+        })
     }
 }
 
 
+/// The number of data sets handled by an incremental index operation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct IncrementalIndexStats {
+    pub added: usize,
+    pub changed: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+}
+
+
 /// Handle Index writing operations such iterating file lists and indexing entries.
 pub struct WindowRefIndexWriter {
     vanilla_path: PathBuf,
@@ -241,7 +1113,7 @@ impl WindowRefIndexWriter {
         index: Index,
         memory_arena_num_bytes: usize
     ) -> Result<Self, TantivyError> {
-        let vanilla_path = vanilla_path.as_ref().to_path_buf().clone();
+        let vanilla_path = vanilla_path.as_ref().to_path_buf();
 
         // Register our custom Tokenizer
         index.tokenizers()
@@ -262,37 +1134,175 @@ impl WindowRefIndexWriter {
         Ok(())
     }
 
-    /// Perform MT indexing operation
-    pub fn index_mt(&mut self) -> Result<(), VanillaError> {
+    /// Get the DataSetAction for a file list
+    fn get_data_set_action(
+        &self,
+        number: usize,
+        location: PathBuf,
+        file_list: WindowsFileList
+    ) -> Result<DataSetAction, VanillaError> {
+        let path = file_list.data_set().path.clone();
+        let hash = file_list.get_content_hash()
+            .map_err(VanillaError::from_message)?;
+
+        Ok( DataSetAction { number, location, file_list, path, hash } )
+    }
+
+    /// Get the DataSetActions for all file lists in the vanilla path
+    fn get_data_set_actions(&self) -> Vec<DataSetAction> {
         let file_list_iter = WinFileListIterator::from_path(
             &self.vanilla_path
         );
 
-        let index = self.index_writer.index();
-        let schema = index.schema();
-
         let mut actions = Vec::new();
-        for (i, (location, file_list)) in file_list_iter.enumerate() {
-            actions.push((i, location, file_list));
+        for (location, file_list) in file_list_iter {
+            match self.get_data_set_action(actions.len(), location, file_list) {
+                Ok(action) => actions.push(action),
+                Err(e) => error!("{}", e)
+            }
         }
+        actions
+    }
+
+    /// Index the data sets in parallel
+    fn index_actions(&self, actions: &[DataSetAction]) {
+        let index = self.index_writer.index();
+        let schema = index.schema();
 
         let size = actions.len();
         actions.par_iter()
-            .for_each(|location_tuple|{
+            .for_each(|action|{
                 if let Err(e) = index_file_list(
                     size, 
-                    location_tuple,
+                    action,
                     &self.index_writer,
                     &schema
                 ) {
                     error!("{:?}", e);
                 }
             });
+    }
+
+    /// Get the data sets in the index (DataSetPath -> DataSetHash)
+    pub fn get_indexed_data_sets(&self) -> Result<HashMap<String, String>, VanillaError> {
+        let index = self.index_writer.index();
+        let reader = index.reader_builder()
+            .reload_policy(ReloadPolicy::Manual)
+            .try_into()?;
+        let searcher = reader.searcher();
+        let schema = searcher.schema();
+
+        let get_field = |name: &str| {
+            schema.get_field(name)
+                .ok_or_else(|| VanillaError::new(
+                    ErrorKind::Index,
+                    format!("Field {} is not in the index schema. The index needs to be rebuilt.", name)
+                ))
+        };
+        let path_field = get_field(FIELD_DATA_SET_PATH)?;
+        let hash_field = get_field(FIELD_DATA_SET_HASH)?;
+
+        // The term dictionary still holds the terms of deleted documents, so
+        // every path is checked for a live document.
+        let mut paths = BTreeSet::new();
+        for segment_reader in searcher.segment_readers() {
+            let inverted_index = segment_reader.inverted_index(path_field)?;
+            let mut stream = inverted_index.terms().stream()?;
+            while stream.advance() {
+                paths.insert(String::from_utf8_lossy(stream.key()).to_string());
+            }
+        }
+
+        let mut data_sets = HashMap::new();
+        for path in paths {
+            let query = TermQuery::new(
+                Term::from_field_text(path_field, &path),
+                IndexRecordOption::Basic
+            );
+            let docs = searcher.search(&query, &TopDocs::with_limit(1))?;
+            if let Some((_score, doc_address)) = docs.first() {
+                let doc = searcher.doc(*doc_address)?;
+                if let Some(hash) = doc.get_first(hash_field).and_then(|v| v.as_text()) {
+                    data_sets.insert(path, hash.to_owned());
+                }
+            }
+        }
+
+        Ok(data_sets)
+    }
+
+    /// Perform MT indexing operation
+    pub fn index_mt(&mut self) -> Result<(), VanillaError> {
+        let actions = self.get_data_set_actions();
+        self.index_actions(&actions);
 
         self.index_writer.commit()?;
 
         Ok(())
     }
+
+    /// Perform an incremental MT indexing operation. Only data sets that are new
+    /// or whose content changed are indexed. Data sets that no longer exist in the
+    /// vanilla path are removed from the index.
+    pub fn index_incremental(&mut self) -> Result<IncrementalIndexStats, VanillaError> {
+        let indexed = self.get_indexed_data_sets()?;
+        let mut stats = IncrementalIndexStats::default();
+
+        let index = self.index_writer.index();
+        let path_field = index.schema()
+            .get_field(FIELD_DATA_SET_PATH)
+            .ok_or_else(|| VanillaError::new(
+                ErrorKind::Index,
+                format!("Field {} is not in the index schema.", FIELD_DATA_SET_PATH)
+            ))?;
+
+        let mut current = HashSet::new();
+        let mut actions = Vec::new();
+        for action in self.get_data_set_actions() {
+            current.insert(action.path.clone());
+
+            match indexed.get(&action.path) {
+                Some(hash) if hash == &action.hash => {
+                    stats.unchanged += 1;
+                    continue;
+                },
+                Some(_) => {
+                    // Remove the old documents before they are indexed again
+                    info!("Data set changed: {}", action.path);
+                    self.index_writer.delete_term(Term::from_field_text(path_field, &action.path));
+                    stats.changed += 1;
+                },
+                None => {
+                    info!("Data set added: {}", action.path);
+                    stats.added += 1;
+                }
+            }
+            actions.push(action);
+        }
+
+        for path in indexed.keys() {
+            if !current.contains(path) {
+                info!("Data set removed: {}", path);
+                self.index_writer.delete_term(Term::from_field_text(path_field, path));
+                stats.removed += 1;
+            }
+        }
+
+        // Renumber for the progress logs
+        for (i, action) in actions.iter_mut().enumerate() {
+            action.number = i;
+        }
+        self.index_actions(&actions);
+
+        self.index_writer.commit()?;
+
+        info!(
+            "Incremental index finished: {} added, {} changed, {} removed, {} unchanged",
+            stats.added, stats.changed, stats.removed, stats.unchanged
+        );
+
+        Ok(stats)
+    }
     
     /// Perform single threaded index operation
     pub fn index(&mut self) -> Result<(), VanillaError> {
@@ -303,74 +1313,26 @@ impl WindowRefIndexWriter {
         let schema = index.schema();
         let file_list_iter = WinFileListIterator::from_path(&self.vanilla_path);
         for (i, (location, file_list)) in file_list_iter.enumerate() {
-            let i = i + 1;
-
-            // Get the record iterator from the file list
-            let record_iter = match file_list.into_iter(){
-                Ok(i) => i,
+            let action = match self.get_data_set_action(i, location, file_list) {
+                Ok(a) => a,
                 Err(e) => {
-                    error!(
-                        "Error handling file list in {}: {}", 
-                        location.to_string_lossy(), e
-                    );
+                    error!("{}", e);
                     continue;
                 }
             };
-
-            info!(
-                "[starting {}/{}] Indexing path: {}",
-                i, set_count,
-                location.to_string_lossy()
-            );
-
-            // Iterate each record
-            for mut record in record_iter {
-                let mut doc = Document::new();
-                // index conversions (all in lowercase)
-                if let Some(n) = record["DirectoryName"].as_str() {
-                    record["DirectoryName"] = json!(n[3..]);
-                }
-                if let Some(n) = record["FullName"].as_str() {
-                    record["FullName"] = json!(n[3..]);
-                }
-                if let Some(n) = record["MD5"].as_str() {
-                    record["MD5"] = json!(n);
-                }
-                if let Some(n) = record["SHA256"].as_str() {
-                    record["SHA256"] = json!(n);
-                }
-
-                for (field, field_entry) in schema.fields() {
-                    let field_name = field_entry.name();
-
-                    // Skip indexing excluded fields
-                    if FIELDS_EXCLUDE.contains(&field_name) {
-                        continue
-                    }
-                    if let Some(field_value) = record.get(field_name) {
-                        if let Some(fv_str) = field_value.as_str() {
-                            doc.add_text(
-                                field,
-                                fv_str
-                            );
-                        }
-                    }
-                }
-
-                if let Err(err) = self.index_writer.add_document(doc){
-                    error!("Error adding document: {:?}", err);
-                };
+            if let Err(e) = index_file_list(
+                set_count,
+                &action,
+                &self.index_writer,
+                &schema
+            ) {
+                error!("{:?}", e);
+                continue;
             }
             self.index_writer.commit()?;
-
-            info!(
-                "[finished {}/{}] Indexing path: {}",
-                i, set_count,
-                location.to_string_lossy()
-            );
         }
 
         info!("[finished] Indexing path: {}", &self.vanilla_path.to_string_lossy());
         Ok(())
     }
-}
\ No newline at end of file
+}
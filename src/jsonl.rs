@@ -0,0 +1,267 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::str::FromStr;
+use serde_json::Value;
+use crate::index::get_hash_field;
+use crate::record::{RecordSource, TriageRecord};
+
+
+/// Get a value from a json object by a dotted path (e.g. `Hash.SHA256`)
+pub fn get_value_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(value, |v, key| v.get(key))
+}
+
+
+/// Get a value as a string. Numbers are converted, empty strings and other
+/// types are ignored.
+fn value_as_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) if !s.trim().is_empty() => Some(s.trim().to_owned()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None
+    }
+}
+
+
+/// Maps the fields of a json record to the values a TriageRecord needs. Field
+/// names can be dotted paths into nested objects (e.g. `Hash.SHA256`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMapping {
+    /// The full path of the file, otherwise it is joined from the directory and name
+    pub path: Option<String>,
+    /// The file name, otherwise it is taken from the path
+    pub name: Option<String>,
+    /// The directory, otherwise it is taken from the path
+    pub directory: Option<String>,
+    pub size: Option<String>,
+    /// MD5 and/or SHA256 fields. The hash type is based on the value's length
+    /// so other hash types (e.g. SHA1) are ignored.
+    pub hashes: Vec<String>,
+    /// A boolean field whose records are skipped when it is true (e.g. directories)
+    pub skip: Option<String>,
+}
+impl FieldMapping {
+    /// The columns of the powershell file lists (and the vanillawinrefs_to_jsonl output)
+    pub fn powershell() -> Self {
+        Self {
+            path: Some("FullName".to_string()),
+            name: Some("Name".to_string()),
+            directory: Some("DirectoryName".to_string()),
+            size: Some("Length".to_string()),
+            hashes: vec!["MD5".to_string(), "SHA256".to_string()],
+            skip: None,
+        }
+    }
+
+    /// Velociraptor `Windows.Search.FileFinder` results
+    pub fn velociraptor() -> Self {
+        Self {
+            path: Some("OSPath".to_string()),
+            name: None,
+            directory: None,
+            size: Some("Size".to_string()),
+            hashes: vec!["Hash.MD5".to_string(), "Hash.SHA256".to_string()],
+            skip: None,
+        }
+    }
+
+    /// The json output of the MFTECmd `$MFT` module of KAPE. It has no full
+    /// path or hashes, so the path is joined from `ParentPath` and `FileName`
+    /// and directories are skipped.
+    pub fn kape() -> Self {
+        Self {
+            path: None,
+            name: Some("FileName".to_string()),
+            directory: Some("ParentPath".to_string()),
+            size: Some("FileSize".to_string()),
+            hashes: Vec::new(),
+            skip: Some("IsDirectory".to_string()),
+        }
+    }
+
+    /// Get a mapping preset by its name
+    pub fn from_preset(name: &str) -> Result<Self, String> {
+        match name.to_lowercase().as_str() {
+            "powershell" => Ok(Self::powershell()),
+            "velociraptor" => Ok(Self::velociraptor()),
+            "kape" => Ok(Self::kape()),
+            other => Err(format!("Unknown field mapping preset '{}'", other))
+        }
+    }
+
+    /// Set the mappings of a `key=field,key=field` string where the keys are
+    /// `path`, `name`, `directory`, `size`, `hash` and `skip`. Each `hash`
+    /// replaces the hash fields of the mapping.
+    pub fn update(&mut self, mappings: &str) -> Result<(), String> {
+        let mut hashes = Vec::new();
+        for mapping in mappings.split(',').map(|m| m.trim()).filter(|m| !m.is_empty()) {
+            let (key, field) = mapping.split_once('=')
+                .ok_or_else(|| format!("Field mapping '{}' is not key=field", mapping))?;
+            let field = field.trim().to_string();
+            match key.trim().to_lowercase().as_str() {
+                "path" => self.path = Some(field),
+                "name" => self.name = Some(field),
+                "directory" => self.directory = Some(field),
+                "size" => self.size = Some(field),
+                "hash" => hashes.push(field),
+                "skip" => self.skip = Some(field),
+                other => return Err(format!("Unknown field mapping key '{}'", other))
+            }
+        }
+        if !hashes.is_empty() {
+            self.hashes = hashes;
+        }
+        Ok(())
+    }
+
+    /// Create a TriageRecord from a json record
+    pub fn to_triage_record(&self, value: &Value) -> Result<TriageRecord, String> {
+        let get = |field: &str| get_value_path(value, field)
+            .and_then(value_as_string);
+
+        let (full_name, directory, name) = match &self.path {
+            Some(path) => {
+                let full_name = get(path)
+                    .ok_or_else(|| format!("Missing value for {}", path))?;
+
+                // Split the path on the last separator if the name or directory is not mapped
+                let (path_directory, path_name) = match full_name.rfind(['\\', '/']) {
+                    Some(i) => (&full_name[..i], &full_name[i + 1..]),
+                    None => ("", full_name.as_str())
+                };
+
+                let name = self.name.as_deref()
+                    .and_then(get)
+                    .unwrap_or_else(|| path_name.to_owned());
+                let directory = self.directory.as_deref()
+                    .and_then(get)
+                    .unwrap_or_else(|| path_directory.to_owned());
+                (full_name, directory, name)
+            },
+            None => {
+                let name_field = self.name.as_deref()
+                    .ok_or("The mapping has no path or name field")?;
+                let name = get(name_field)
+                    .ok_or_else(|| format!("Missing value for {}", name_field))?;
+                let directory = self.directory.as_deref()
+                    .and_then(get)
+                    .unwrap_or_default();
+
+                let full_name = if directory.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{}\\{}", directory.trim_end_matches(['\\', '/']), name)
+                };
+                (full_name, directory, name)
+            }
+        };
+
+        let length = match self.size.as_deref().and_then(get) {
+            Some(size) => Some(
+                size.parse::<u64>()
+                    .map_err(|e| format!("Invalid size '{}': {}", size, e))?
+            ),
+            None => None
+        };
+
+        let mut record = TriageRecord {
+            full_name,
+            directory,
+            name,
+            length,
+            ..Default::default()
+        };
+        for hash in self.hashes.iter().filter_map(|field| get(field)) {
+            match get_hash_field(&hash) {
+                Ok("MD5") => record.md5 = Some(hash),
+                Ok("SHA256") => record.sha256 = Some(hash),
+                _ => {}
+            }
+        }
+
+        Ok(record)
+    }
+
+    /// Check if a json record is skipped by the `skip` field
+    pub fn is_skipped(&self, value: &Value) -> bool {
+        self.skip.as_deref()
+            .and_then(|field| get_value_path(value, field))
+            .is_some_and(|v| match v {
+                Value::Bool(b) => *b,
+                Value::String(s) => s.trim().eq_ignore_ascii_case("true"),
+                _ => false
+            })
+    }
+}
+impl Default for FieldMapping {
+    fn default() -> Self {
+        Self::powershell()
+    }
+}
+impl FromStr for FieldMapping {
+    type Err = String;
+
+    /// Parse a `key=field,key=field` string on top of the default mapping
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut mapping = Self::default();
+        mapping.update(s)?;
+        Ok(mapping)
+    }
+}
+
+
+/// Reads TriageRecords from a file with a json record per line. Malformed lines
+/// are returned as errors that contain the (1 based) line number.
+pub struct JsonlRecordSource<R: BufRead> {
+    mapping: FieldMapping,
+    reader: R,
+    line: usize
+}
+impl<R: BufRead> JsonlRecordSource<R> {
+    /// Create a JsonlRecordSource from a reader
+    pub fn new(reader: R, mapping: FieldMapping) -> Self {
+        Self {
+            mapping,
+            reader,
+            line: 0
+        }
+    }
+}
+impl JsonlRecordSource<BufReader<File>> {
+    /// Open a jsonl file
+    pub fn from_path(path: impl AsRef<Path>, mapping: FieldMapping) -> Result<Self, String> {
+        let path = path.as_ref();
+        let fh = File::open(path)
+            .map_err(|e| format!("Could not open '{}'. {:?}", path.to_string_lossy(), e))?;
+        Ok(Self::new(BufReader::new(fh), mapping))
+    }
+}
+impl<R: BufRead> RecordSource for JsonlRecordSource<R> {
+    fn next_record(&mut self) -> Option<Result<TriageRecord, String>> {
+        let mut buffer = String::new();
+        loop {
+            buffer.clear();
+            self.line += 1;
+            match self.reader.read_line(&mut buffer) {
+                Ok(0) => return None,
+                Ok(_) => {},
+                Err(e) => return Some(Err(format!("line {}: {:?}", self.line, e)))
+            }
+
+            // Skip blank lines and the BOM some tools write
+            let line = buffer.trim_start_matches('\u{feff}').trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let result = match serde_json::from_str::<Value>(line) {
+                Ok(value) if self.mapping.is_skipped(&value) => continue,
+                Ok(value) => self.mapping.to_triage_record(&value),
+                Err(e) => Err(format!("{}", e))
+            };
+            return Some(result.map_err(|e| format!("line {}: {}", self.line, e)));
+        }
+    }
+}
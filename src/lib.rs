@@ -9,4 +9,26 @@ pub mod error;
 /// Custom tokenizer for indexing
 pub mod tokenizer;
 /// VanillaWindowsReference helpers/operations
-pub mod vanilla;
\ No newline at end of file
+pub mod vanilla;
+/// Platform independent Windows paths
+pub mod winpath;
+/// Files of host listings to triage
+pub mod record;
+/// MFTECmd csv helpers
+pub mod mftecmd;
+/// JSONL file listing helpers
+pub mod jsonl;
+/// Output formats of reference records
+pub mod output;
+/// Hash set exports of the reference
+pub mod export;
+/// Compact hash filters of the reference
+pub mod filter;
+/// Look-alike file name detection
+pub mod similar;
+/// Classify files against the index
+pub mod verdict;
+/// Check host file listings against the index
+pub mod triage;
+/// Differences between two builds
+pub mod diff;
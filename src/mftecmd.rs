@@ -0,0 +1,146 @@
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use crate::record::{RecordSource, TriageRecord};
+use crate::vanilla::{parse_timestamp, CsvColumnReader, CsvRow};
+use crate::winpath::WindowsPath;
+
+/// Columns a MFTECmd csv needs to be read into MftRecords
+const REQUIRED_COLUMNS: &[&str] = &[
+    "ParentPath", "FileName", "FileSize", "IsDirectory", "InUse"
+];
+
+
+/// Parse the `True`/`False` values MFTECmd writes
+fn parse_bool(value: &str) -> Result<bool, String> {
+    match value.trim() {
+        v if v.eq_ignore_ascii_case("true") => Ok(true),
+        v if v.eq_ignore_ascii_case("false") || v.is_empty() => Ok(false),
+        v => Err(format!("Unable to parse bool '{}'", v))
+    }
+}
+
+
+/// A row of a MFTECmd `$MFT` csv parse.
+#[derive(Debug, Clone)]
+pub struct MftRecord {
+    pub entry_number: Option<u64>,
+    /// The record is in use (the file is not deleted)
+    pub in_use: bool,
+    /// The parent path relative to the volume root (e.g. `.\Windows\System32`)
+    pub parent_path: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub is_directory: bool,
+    /// The record is an alternate data stream of a file
+    pub is_ads: bool,
+    /// The $STANDARD_INFORMATION creation time
+    pub created: Option<DateTime<Utc>>,
+}
+impl MftRecord {
+    /// Create a record from a csv row
+    fn from_csv_row(row: &CsvRow) -> Result<Self, String> {
+        let boolean = |name: &str| -> Result<bool, String> {
+            parse_bool(row.get(name).unwrap_or_default())
+                .map_err(|e| format!("Invalid {}: {}", name, e))
+        };
+
+        let file_size = row.required("FileSize")?;
+        let file_size = file_size.trim()
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid FileSize '{}': {}", file_size, e))?;
+
+        let entry_number = match row.optional("EntryNumber") {
+            Some(v) => Some(
+                v.parse::<u64>()
+                    .map_err(|e| format!("Invalid EntryNumber '{}': {}", v, e))?
+            ),
+            None => None
+        };
+
+        // A timestamp that can't be parsed does not fail the row
+        let created = row.optional("Created0x10")
+            .and_then(|v| parse_timestamp(v).ok());
+
+        Ok( Self {
+            entry_number,
+            in_use: boolean("InUse")?,
+            parent_path: row.required("ParentPath")?.to_owned(),
+            file_name: row.required("FileName")?.to_owned(),
+            file_size,
+            is_directory: boolean("IsDirectory")?,
+            is_ads: boolean("IsAds")?,
+            created,
+        })
+    }
+
+    /// Check if the record is an allocated file (not a directory, alternate data
+    /// stream or deleted entry), which are the only records that can be triaged.
+    pub fn is_file(&self) -> bool {
+        self.in_use && !self.is_directory && !self.is_ads
+    }
+
+    /// The parent path without the `.` MFTECmd uses for the volume root and
+    /// the drive letter (e.g. `Windows\System32`)
+    pub fn directory_name(&self) -> String {
+        WindowsPath::from_directory(&self.parent_path)
+            .relative_path()
+            .to_string()
+    }
+
+    /// The full path relative to the volume root (e.g. `Windows\System32\cmd.exe`)
+    pub fn full_name(&self) -> String {
+        let directory = self.directory_name();
+        if directory.is_empty() {
+            self.file_name.clone()
+        } else {
+            format!(r"{}\{}", directory, self.file_name)
+        }
+    }
+}
+impl From<MftRecord> for TriageRecord {
+    fn from(record: MftRecord) -> Self {
+        Self {
+            full_name: record.full_name(),
+            directory: record.directory_name(),
+            name: record.file_name,
+            length: Some(record.file_size),
+            md5: None,
+            sha256: None,
+        }
+    }
+}
+
+
+/// This iterator reads MftRecords from a MFTECmd csv. Malformed rows are returned
+/// as errors that contain the (1 based) row number, not counting the header.
+pub struct MftECmdRecordIterator {
+    rows: CsvColumnReader
+}
+impl MftECmdRecordIterator {
+    /// Open a MFTECmd csv. Errors out if a required column is missing from the
+    /// CSV header.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        Ok( Self {
+            rows: CsvColumnReader::from_path(path, REQUIRED_COLUMNS)?
+        })
+    }
+}
+impl Iterator for MftECmdRecordIterator {
+    type Item = Result<MftRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next_row(MftRecord::from_csv_row)
+    }
+}
+impl RecordSource for MftECmdRecordIterator {
+    /// Only allocated files are returned
+    fn next_record(&mut self) -> Option<Result<TriageRecord, String>> {
+        for result in self.by_ref() {
+            match result {
+                Ok(record) if !record.is_file() => continue,
+                result => return Some(result.map(TriageRecord::from))
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use serde_json::{Map, Value};
+
+
+/// The formats reference records can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordFormat {
+    /// One JSON object per line
+    Jsonl,
+    Csv,
+    Tsv,
+    /// A single JSON object with an array of values per column
+    Columns,
+}
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            "tsv" => Ok(Self::Tsv),
+            "columns" => Ok(Self::Columns),
+            other => Err(format!("Unknown record format '{}'", other))
+        }
+    }
+}
+impl fmt::Display for RecordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Jsonl => write!(f, "jsonl"),
+            Self::Csv => write!(f, "csv"),
+            Self::Tsv => write!(f, "tsv"),
+            Self::Columns => write!(f, "columns"),
+        }
+    }
+}
+
+
+/// Get the csv cell of a record value. Lists (such as Hotfixes) are joined
+/// with `|`.
+pub fn get_cell_value(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.to_owned(),
+        Value::Array(values) => values.iter()
+            .map(get_cell_value)
+            .collect::<Vec<String>>()
+            .join("|"),
+        other => other.to_string()
+    }
+}
+
+
+/// Writes reference records (see `WinFileListRecordIterator`) with a fixed set
+/// of columns in a RecordFormat. Columns a record does not have are left
+/// empty (csv/tsv), null (columns) or out (jsonl).
+pub enum RecordWriter<W: Write> {
+    Jsonl {
+        writer: W,
+        columns: Vec<String>,
+    },
+    Delimited {
+        writer: Box<csv::Writer<W>>,
+        columns: Vec<String>,
+    },
+    Columns {
+        writer: W,
+        columns: Vec<String>,
+        values: Vec<Vec<Value>>,
+    },
+}
+impl<W: Write> RecordWriter<W> {
+    /// Create a RecordWriter. For csv/tsv the header is written right away.
+    pub fn new(writer: W, format: RecordFormat, columns: Vec<String>) -> Result<Self, String> {
+        match format {
+            RecordFormat::Jsonl => Ok(Self::Jsonl { writer, columns }),
+            RecordFormat::Csv | RecordFormat::Tsv => {
+                let delimiter = if format == RecordFormat::Tsv { b'\t' } else { b',' };
+                let mut csv_writer = csv::WriterBuilder::new()
+                    .delimiter(delimiter)
+                    .from_writer(writer);
+                csv_writer.write_record(&columns)
+                    .map_err(|e| format!("{:?}", e))?;
+                Ok(Self::Delimited { writer: Box::new(csv_writer), columns })
+            },
+            RecordFormat::Columns => {
+                let values = vec![Vec::new(); columns.len()];
+                Ok(Self::Columns { writer, columns, values })
+            }
+        }
+    }
+
+    /// Write a single record. The columnar format holds the values until
+    /// `finish` is called.
+    pub fn write_record(&mut self, record: &Value) -> Result<(), String> {
+        match self {
+            Self::Jsonl { writer, columns } => {
+                let object: Map<String, Value> = columns.iter()
+                    .filter_map(|c| record.get(c).map(|v| (c.to_owned(), v.clone())))
+                    .collect();
+                writeln!(writer, "{}", Value::Object(object))
+                    .map_err(|e| format!("{:?}", e))?;
+            },
+            Self::Delimited { writer, columns } => {
+                let row = columns.iter()
+                    .map(|c| record.get(c).map(get_cell_value).unwrap_or_default());
+                writer.write_record(row)
+                    .map_err(|e| format!("{:?}", e))?;
+            },
+            Self::Columns { columns, values, .. } => {
+                for (column, column_values) in columns.iter().zip(values.iter_mut()) {
+                    column_values.push(record.get(column).cloned().unwrap_or(Value::Null));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Write anything that is pending, flush and return the underlying writer.
+    pub fn finish(self) -> Result<W, String> {
+        let mut writer = match self {
+            Self::Jsonl { writer, .. } => writer,
+            Self::Delimited { writer, .. } => writer.into_inner()
+                .map_err(|e| format!("{:?}", e.error()))?,
+            Self::Columns { mut writer, columns, values } => {
+                let object: Map<String, Value> = columns.into_iter()
+                    .zip(values.into_iter().map(Value::Array))
+                    .collect();
+                serde_json::to_writer(&mut writer, &object)
+                    .map_err(|e| format!("{:?}", e))?;
+                writeln!(writer)
+                    .map_err(|e| format!("{:?}", e))?;
+                writer
+            }
+        };
+        writer.flush()
+            .map_err(|e| format!("{:?}", e))?;
+        Ok(writer)
+    }
+}
@@ -0,0 +1,63 @@
+use lazy_static::lazy_static;
+use crate::jsonl::FieldMapping;
+use crate::vanilla::{CsvFileRecordIterator, VanillaFileRecord, WinFileListRecordIterator};
+
+lazy_static! {
+    /// The mapping of the json records of a WinFileListRecordIterator
+    static ref POWERSHELL_MAPPING: FieldMapping = FieldMapping::powershell();
+}
+
+
+/// A file from a host listing that should be checked against the index.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TriageRecord {
+    /// The path as it was listed on the host (e.g. `C:\Windows\System32\cmd.exe`)
+    pub full_name: String,
+    /// The directory as it was listed on the host (e.g. `C:\Windows\System32`)
+    pub directory: String,
+    pub name: String,
+    pub length: Option<u64>,
+    pub md5: Option<String>,
+    pub sha256: Option<String>,
+}
+impl TriageRecord {
+    /// The hash to check, SHA256 is preferred over MD5.
+    pub fn get_hash(&self) -> Option<&str> {
+        self.sha256.as_deref()
+            .or(self.md5.as_deref())
+    }
+}
+impl From<VanillaFileRecord> for TriageRecord {
+    fn from(record: VanillaFileRecord) -> Self {
+        Self {
+            full_name: record.full_name.to_string(),
+            directory: record.directory_name.to_string(),
+            name: record.name,
+            length: Some(record.length),
+            md5: record.md5,
+            sha256: record.sha256,
+        }
+    }
+}
+
+
+/// A source of files to triage such as a host file listing. This lets the same
+/// comparison run on any listing format. The file list iterators of the
+/// reference implement it here, other listing formats in their own module.
+pub trait RecordSource {
+    /// Get the next file of the source or None when the source is exhausted.
+    /// Records that can't be read are returned as errors.
+    fn next_record(&mut self) -> Option<Result<TriageRecord, String>>;
+}
+impl RecordSource for CsvFileRecordIterator {
+    fn next_record(&mut self) -> Option<Result<TriageRecord, String>> {
+        self.next()
+            .map(|r| r.map(TriageRecord::from))
+    }
+}
+impl RecordSource for WinFileListRecordIterator {
+    fn next_record(&mut self) -> Option<Result<TriageRecord, String>> {
+        self.next()
+            .map(|value| POWERSHELL_MAPPING.to_triage_record(&value))
+    }
+}
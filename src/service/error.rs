@@ -0,0 +1,72 @@
+use serde_json::json;
+use rocket::{catch, Request};
+use rocket::http::Status;
+use rocket::response::{self, Responder, Response};
+use rocket::serde::json::Value;
+use crate::error::{ErrorKind, VanillaError};
+
+
+/// Get the HTTP status for an ErrorKind
+fn get_status(kind: ErrorKind) -> Status {
+    match kind {
+        ErrorKind::InvalidInput => Status::UnprocessableEntity,
+        ErrorKind::InvalidQuery => Status::BadRequest,
+        ErrorKind::NotFound => Status::NotFound,
+        ErrorKind::Index
+        | ErrorKind::Io
+        | ErrorKind::Git
+        | ErrorKind::Other => Status::InternalServerError,
+    }
+}
+
+
+/// Create the `{error, detail}` body that is returned for all errors
+fn error_body(error: &str, detail: &str) -> Value {
+    json!({
+        "error": error,
+        "detail": detail
+    })
+}
+
+
+/// Respond to errors with the matching status and a JSON body.
+impl<'r> Responder<'r, 'static> for VanillaError {
+    fn respond_to(self, request: &'r Request<'_>) -> response::Result<'static> {
+        let status = get_status(self.kind());
+        if status == Status::InternalServerError {
+            error!("{} {}: {}", request.method(), request.uri(), self);
+        }
+
+        let body = error_body(self.kind().as_str(), self.message());
+        Response::build_from(body.respond_to(request)?)
+            .status(status)
+            .ok()
+    }
+}
+
+
+#[catch(404)]
+pub fn not_found(request: &Request) -> Value {
+    error_body(
+        "NotFound",
+        &format!("No route for {} {}", request.method(), request.uri())
+    )
+}
+
+
+#[catch(422)]
+pub fn unprocessable_entity(_request: &Request) -> Value {
+    error_body(
+        "UnprocessableEntity",
+        "The request body could not be parsed into the expected JSON structure."
+    )
+}
+
+
+#[catch(default)]
+pub fn default_catcher(status: Status, _request: &Request) -> Value {
+    error_body(
+        status.reason().unwrap_or("Error"),
+        &format!("The request failed with status {}.", status.code)
+    )
+}
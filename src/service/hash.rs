@@ -1,67 +1,48 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 use serde::{Serialize, Deserialize};
 use serde_json::json;
 use rocket::{post, State};
 use rocket::serde::json::Json;
-use crate::index::WindowsRefIndexReader;
+use crate::error::VanillaError;
+use crate::index::{get_hash_field, LookupFilter, WindowsRefIndexReader};
+use crate::service::aggregate_hits;
+
+/// Fields returned for each hash of a batch lookup
+const BATCH_FIELDS: &[&str] = &["OsName", "OsVersion", "FullName"];
+/// The most values of a batch lookup
+const MAX_BATCH_SIZE: usize = 1000;
+/// The most hits aggregated for each hash of a batch lookup
+const BATCH_HIT_LIMIT: usize = 1000;
 
 
 #[derive(Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct HashLookup {
-    value: String
+    value: String,
+    #[serde(flatten)]
+    filter: LookupFilter
+}
+
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct HashBatchLookup {
+    values: Vec<String>,
+    #[serde(flatten)]
+    filter: LookupFilter
 }
 
 
 fn resolve(
-    key: &str,
     lookup: &HashLookup,
     index_reader: &State<WindowsRefIndexReader>,
-) -> serde_json::Value {
-    let mut aggregation: HashMap<String, HashSet<String>> = HashMap::new();
-    let known_value;
-
-    // Create index query
-    let query = format!("{}:\"{}\"", &key, &lookup.value.to_lowercase());
-
+) -> Result<serde_json::Value, VanillaError> {
     // Get hits
-    let mut hits = index_reader.get_query_hits(&query, 1000)
-        .expect("Query error.");
-
-    // Set aggregation from first record
-    if let Some(first_record) = hits.pop() {
-        let object = first_record.as_object()
-            .expect("Record is not an object!");
-
-        for (k, v) in object {
-            let v = v[0].as_str()
-                .expect(&format!("First value is not a string. {}", v));
-
-            aggregation.insert(
-                k.to_owned(),
-                HashSet::from([v.to_owned()])
-            );
-        }
-        known_value = Some(true);
-    } else {
-        known_value = Some(false);
-    }
+    let hits = index_reader.lookup_by_hash(&lookup.value, &lookup.filter, 1000)?;
 
-    // Aggregate the rest of the hits
-    for hit in hits {
-        if let Some(object) = hit.as_object() {
-            for (k, v) in object {
-                let v = v[0].as_str().expect("First value is not a string.");
-                if let Some(set) = aggregation.get_mut(k) {
-                    set.insert(v.to_owned());
-                }
-            }
-        }
-    }
+    let aggregation = aggregate_hits(&hits);
 
-    let return_value = json!(aggregation);
-
-    return_value
+    Ok(json!(aggregation))
 }
 
 
@@ -69,16 +50,82 @@ fn resolve(
 pub fn lookup_hash(
     index_reader: &State<WindowsRefIndexReader>,
     hash_lookup: Json<HashLookup>
-) -> Result<serde_json::Value, String> {
-    let key = match hash_lookup.value.len() {
-        32 => "MD5",
-        64 => "SHA256",
-        len => {
-            return Err(format!("Unhandled hash type with length: {}", len));
+) -> Result<serde_json::Value, VanillaError> {
+    resolve(&hash_lookup, index_reader)
+}
+
+
+/// Lookup many MD5/SHA256 values (can be mixed) at once. Every hash gets a
+/// `Known` verdict along with the OS versions and paths it was seen in, which
+/// are `Truncated` past 1000 hits. Values that are not a valid hash length get an
+/// `Error` instead. At most 1000 values can be looked up at once.
+#[post("/api/v1/lookup/hash/batch", format="json", data="<batch_lookup>")]
+pub fn lookup_hash_batch(
+    index_reader: &State<WindowsRefIndexReader>,
+    batch_lookup: Json<HashBatchLookup>
+) -> Result<serde_json::Value, VanillaError> {
+    if batch_lookup.values.len() > MAX_BATCH_SIZE {
+        return Err(VanillaError::invalid_input(
+            format!("batch of {} values is greater than {}", batch_lookup.values.len(), MAX_BATCH_SIZE)
+        ));
+    }
+
+    // Remove duplicate values while keeping the order
+    let mut seen = HashSet::new();
+    let values = batch_lookup.values.iter()
+        .map(|v| v.trim().to_lowercase())
+        .filter(|v| seen.insert(v.clone()))
+        .collect::<Vec<String>>();
+
+    // All valid hashes are looked up with the same searcher
+    let hashes = values.iter()
+        .filter(|v| get_hash_field(v).is_ok())
+        .cloned()
+        .collect::<Vec<String>>();
+    let mut hits_iter = index_reader.lookup_by_hashes(&hashes, &batch_lookup.filter, BATCH_HIT_LIMIT + 1)?
+        .into_iter();
+
+    let mut known_count = 0;
+    let mut invalid_count = 0;
+    let mut results = Vec::with_capacity(values.len());
+    for value in values {
+        if let Err(e) = get_hash_field(&value) {
+            invalid_count += 1;
+            results.push(json!({
+                "Value": value,
+                "Known": null,
+                "Error": e
+            }));
+            continue;
+        }
+
+        let mut hits = hits_iter.next().unwrap_or_default();
+        let truncated = hits.len() > BATCH_HIT_LIMIT;
+        hits.truncate(BATCH_HIT_LIMIT);
+        let known = !hits.is_empty();
+        if known {
+            known_count += 1;
         }
-    };
 
-    let result = resolve(key, &hash_lookup, index_reader);
+        let aggregation = aggregate_hits(&hits);
+        let mut result = json!({
+            "Value": value,
+            "Known": known,
+            "Truncated": truncated
+        });
+        for field in BATCH_FIELDS {
+            let values = aggregation.get(*field)
+                .cloned()
+                .unwrap_or_default();
+            result[*field] = json!(values);
+        }
+        results.push(result);
+    }
 
-    Ok( result )
+    Ok(json!({
+        "KnownCount": known_count,
+        "UnknownCount": results.len() - known_count - invalid_count,
+        "InvalidCount": invalid_count,
+        "Results": results
+    }))
 }
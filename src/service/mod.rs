@@ -1,2 +1,51 @@
+use std::collections::{HashMap, HashSet};
+use serde_json::Value;
+
 pub mod path;
-pub mod hash;
\ No newline at end of file
+pub mod hash;
+pub mod search;
+pub mod error;
+
+
+/// Get the first value of a named document field as a string
+fn first_value(values: &Value) -> Option<String> {
+    values.get(0)
+        .map(|v| match v.as_str() {
+            Some(s) => s.to_owned(),
+            None => v.to_string()
+        })
+}
+
+
+/// Aggregate the first value of each field across a list of hits (named
+/// documents). The fields are the ones of the last hit.
+fn aggregate_hits(hits: &[Value]) -> HashMap<String, HashSet<String>> {
+    let mut aggregation: HashMap<String, HashSet<String>> = HashMap::new();
+
+    // Set aggregation from the last record
+    let (last_record, hits) = match hits.split_last() {
+        Some(split) => split,
+        None => return aggregation
+    };
+    if let Some(object) = last_record.as_object() {
+        for (k, v) in object {
+            aggregation.insert(
+                k.to_owned(),
+                first_value(v).into_iter().collect()
+            );
+        }
+    }
+
+    // Aggregate the rest of the hits
+    for hit in hits {
+        if let Some(object) = hit.as_object() {
+            for (k, v) in object {
+                if let (Some(set), Some(v)) = (aggregation.get_mut(k), first_value(v)) {
+                    set.insert(v);
+                }
+            }
+        }
+    }
+
+    aggregation
+}
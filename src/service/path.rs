@@ -1,27 +1,42 @@
-use std::time::{Duration, Instant};
-use std::collections::{HashMap, HashSet};
-use std::path::Path;
+use std::time::Instant;
 use serde::{Serialize, Deserialize};
 use serde_json::json;
-use lazy_static::lazy_static;
-use regex::Regex;
 use rocket::{post, State};
 use rocket::serde::json::Json;
 use crate::error::VanillaError;
-use crate::index::WindowsRefIndexReader;
+use crate::index::{LookupFilter, WindowsRefIndexReader};
+use crate::verdict::{get_name_directories, get_name_path_verdict, Verdict};
+use crate::service::aggregate_hits;
+use crate::winpath::{get_redirected_directories, WindowsPath};
 
-lazy_static! {
-    static ref RE_LETTER: Regex = Regex::new(r"(?i)^[a-z]:\\").unwrap();
-}
+/// The number of similar names added to unknown name responses
+const SIMILAR_NAME_HINT_LIMIT: usize = 5;
+/// The number of similar names returned when no limit is given
+const DEFAULT_SIMILAR_NAME_LIMIT: usize = 100;
+/// The max number of similar names returned
+const MAX_SIMILAR_NAME_LIMIT: usize = 1000;
 
 
 #[derive(Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct FileNameLookup {
     value: String,
-    path: Option<String>
+    path: Option<String>,
+    /// Treat WOW64/ARM64 redirected directories as the same directory
+    #[serde(default)]
+    redirect: bool,
+    #[serde(flatten)]
+    filter: LookupFilter
 }
 impl FileNameLookup {
+    /// Make the path volume relative (e.g. `C:\Windows` -> `Windows`)
+    fn normalize_path(&mut self) {
+        if let Some(path) = self.path.as_mut() {
+            *path = WindowsPath::from_directory(path).relative_path().to_string();
+        }
+    }
+
+    #[allow(clippy::manual_map)]
     fn get_lookup_path(&self) -> Option<String> {
         if let Some(path) = &self.path {
             Some(path.replace(r"/", r"\").to_lowercase())
@@ -29,153 +44,166 @@ impl FileNameLookup {
             None
         }
     }
+
+    /// The lowercase directories the file can be in. With `redirect` the
+    /// redirected directories follow the path itself.
+    fn get_lookup_paths(&self) -> Option<Vec<String>> {
+        let path = self.get_lookup_path()?;
+        if self.redirect {
+            Some(get_redirected_directories(&path).into_iter()
+                .map(|p| p.to_lowercase())
+                .collect())
+        } else {
+            Some(vec![path])
+        }
+    }
 }
 
 
 #[derive(Deserialize, Serialize)]
 #[serde(crate = "rocket::serde")]
 pub struct FullPathLookup {
-    value: String
+    value: String,
+    hash: Option<String>,
+    /// Treat WOW64/ARM64 redirected directories as the same directory
+    #[serde(default)]
+    redirect: bool,
+    #[serde(flatten)]
+    filter: LookupFilter
 }
 impl FullPathLookup {
-    fn as_file_name_lookup(&self) -> Result<FileNameLookup, VanillaError> {
-        let os_path = self.as_os_path();
-        let full_path = Path::new(&os_path);
-
-        let value = full_path.file_name()
-            .ok_or(VanillaError::from_message(
-                format!("Could not get file_name from {}", full_path.to_string_lossy())
-            ))?
-            .to_string_lossy()
-            .to_string();
-
-        let path = full_path.parent()
-            .ok_or(VanillaError::from_message(
-                format!("Could not get parent from {}", full_path.to_string_lossy())
-            ))?
-            .to_string_lossy()
-            .to_string();
-
-        Ok( FileNameLookup { value, path: Some(path) })
+    /// The volume relative full path (e.g. `Windows\System32\cmd.exe`)
+    fn get_windows_path(&self) -> WindowsPath {
+        WindowsPath::new(&self.value)
     }
 
-    fn as_os_path(&self) -> String {
-        #[cfg(target_family="windows")]
-        {self.value.replace("/", r"\")}
-        #[cfg(not(target_family="windows"))]
-        {self.value.replace(r"\", "/")}
+    fn as_file_name_lookup(&self) -> Result<FileNameLookup, VanillaError> {
+        let full_path = self.get_windows_path();
+        if full_path.file_name().is_empty() {
+            return Err(VanillaError::invalid_input(
+                format!("Could not get file_name from '{}'", self.value)
+            ));
+        }
+
+        Ok( FileNameLookup {
+            value: full_path.file_name().to_string(),
+            path: Some(full_path.parent().to_string()),
+            redirect: self.redirect,
+            filter: self.filter.clone()
+        })
     }
 }
 
 
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct SimilarNameLookup {
+    value: String,
+    limit: Option<usize>
+}
+
+
+/// Add the known names that look like an unknown name as `SimilarNames`
+fn add_similar_names(
+    result: &mut serde_json::Value,
+    name: &str,
+    index_reader: &WindowsRefIndexReader
+) -> Result<(), VanillaError> {
+    let similar = index_reader.get_similar_names(name, SIMILAR_NAME_HINT_LIMIT)?;
+    result["SimilarNames"] = json!(similar);
+    Ok(())
+}
+
+
 fn resolve(
-    key: &str,
     lookup: &FileNameLookup,
     index_reader: &State<WindowsRefIndexReader>,
-) -> serde_json::Value {
-    let mut aggregation: HashMap<String, HashSet<String>> = HashMap::new();
-    let known_value;
+) -> Result<serde_json::Value, VanillaError> {
     let mut known_path = None;
 
-    // Create index query
-    let query = format!("{}:\"{}\"", &key, &lookup.value.to_lowercase());
-
     // Get hits
-    let mut hits = index_reader.get_query_hits(&query, 1000)
-        .expect("Query error.");
-
-    // Set aggregation from first record
-    if let Some(first_record) = hits.pop() {
-        let object = first_record.as_object()
-            .expect("Record is not an object!");
-
-        for (k, v) in object {
-            let v = v[0].as_str()
-                .expect(&format!("First value is not a string. {}", v));
-
-            aggregation.insert(
-                k.to_owned(),
-                HashSet::from([v.to_owned()])
-            );
-        }
-        known_value = Some(true);
-    } else {
-        known_value = Some(false);
-    }
+    let hits = index_reader.lookup_by_name(&lookup.value, &lookup.filter, 1000)?;
 
-    // Aggregate the rest of the hits
-    for hit in hits {
-        if let Some(object) = hit.as_object() {
-            for (k, v) in object {
-                let v = v[0].as_str().expect("First value is not a string.");
-                if let Some(set) = aggregation.get_mut(k) {
-                    set.insert(v.to_owned());
-                }
-            }
-        }
-    }
+    let known_value = !hits.is_empty();
+    let aggregation = aggregate_hits(&hits);
 
     let mut return_value = json!(aggregation);
-    if let Some(path) = &lookup.get_lookup_path() {
+    let mut matched_path = None;
+    if let Some(paths) = &lookup.get_lookup_paths() {
         if let Some(dir_names) = aggregation.get("DirectoryName") {
-            let lower_paths = dir_names.iter()
-                .map(|v|v.to_lowercase())
-                .collect::<Vec<String>>();
-
-            if lower_paths.contains(path) {
-                known_path = Some(true);
-            } else {
-                known_path = Some(false);
-            }
+            // The first path (in redirect order) that a hit is in
+            matched_path = paths.iter()
+                .find_map(|path| dir_names.iter().find(|d| d.to_lowercase() == *path));
+
+            known_path = Some(matched_path.is_some());
         }
     }
 
     return_value["KnownName"] = json!(known_value);
     return_value["KnownPath"] = json!(known_path);
+    if lookup.redirect {
+        return_value["MatchedPath"] = json!(matched_path);
+    }
+    if !known_value {
+        add_similar_names(&mut return_value, &lookup.value, index_reader)?;
+    }
+
+    Ok(return_value)
+}
+
 
-    return_value
+/// Get a lowercase directory as it was indexed (e.g. `windows\syswow64` ->
+/// `Windows\SysWOW64`) if the name is in it.
+fn get_indexed_directory(
+    name_lookup: &FileNameLookup,
+    path: &str,
+    index_reader: &WindowsRefIndexReader,
+) -> Result<Option<String>, VanillaError> {
+    let hits = index_reader.lookup_by_name_in_directory(&name_lookup.value, path, &name_lookup.filter, 1)?;
+    Ok(hits.first()
+        .and_then(|hit| hit.get("DirectoryName"))
+        .and_then(|v| v.get(0))
+        .and_then(|v| v.as_str())
+        .map(|v| v.to_owned()))
 }
 
 
+#[allow(clippy::len_zero)]
 pub fn known_lookup(
     name_lookup: &FileNameLookup,
     index_reader: &State<WindowsRefIndexReader>,
-) -> serde_json::Value {
+) -> Result<serde_json::Value, VanillaError> {
     let mut result = json!({});
 
-    // Create index query
-    let query_known_name = format!("Name:\"{}\"", &name_lookup.value.to_lowercase());
-
     // Get hits
-    let hits = index_reader.get_query_hits(&query_known_name, 1)
-        .expect("Query error.");
+    let hits = index_reader.lookup_by_name(&name_lookup.value, &name_lookup.filter, 1)?;
 
     if hits.len() > 0 {
         result["KnownName"] = json!(true);
     } else {
         result["KnownName"] = json!(false);
+        add_similar_names(&mut result, &name_lookup.value, index_reader)?;
     }
 
-    if let Some(path) = &name_lookup.get_lookup_path() {
-        let query_known_path = format!(
-            "Name:\"{}\" AND DirectoryName:\"{}\"", 
-            &name_lookup.value.to_lowercase(),
-            path
-        );
-        // Get hits
-        let hits = index_reader.get_query_hits(&query_known_path, 1)
-            .expect("Query error.");
-        
-        if hits.len() > 0 {
-            result["KnownPath"] = json!(true);
-        } else {
-            result["KnownPath"] = json!(false);
+    if let Some(paths) = &name_lookup.get_lookup_paths() {
+        // The first path that has a hit
+        let mut matched_path = None;
+        for path in paths {
+            matched_path = get_indexed_directory(name_lookup, path, index_reader)?;
+            if matched_path.is_some() {
+                break;
+            }
+        }
+
+        result["KnownPath"] = json!(matched_path.is_some());
+        if name_lookup.redirect {
+            result["MatchedPath"] = json!(matched_path);
         }
     } else {
         result["KnownPath"] = serde_json::Value::Null;
     }
 
-    result
+    Ok(result)
 }
 
 
@@ -183,48 +211,84 @@ pub fn known_lookup(
 pub fn known_file_name(
     index_reader: &State<WindowsRefIndexReader>,
     mut name_lookup: Json<FileNameLookup>
-) -> serde_json::Value {
+) -> Result<serde_json::Value, VanillaError> {
     let start = Instant::now();
 
-    if let Some(path) = name_lookup.path.as_mut() {
-        *path = path.trim_start_matches(r"\").to_string();
-        *path = path.trim_start_matches(r"/").to_string();
-        *path = RE_LETTER.replace(&path, "").to_string();
-    }
+    name_lookup.normalize_path();
 
     let result = known_lookup(
         &name_lookup,
         index_reader
-    );
+    )?;
 
     let duration = start.elapsed();
     info!("Time elapsed in known_file_name() is: {:?}", duration);
 
-    result
+    Ok(result)
 }
 
 
+/// Known lookup for a full path. When a hash is given, `KnownHash` is set and
+/// if the hash was never seen at the path, `SeenHashes` lists the hashes that
+/// were seen there along with their OS versions.
 #[post("/api/v1/known/fullname", format="json", data="<name_lookup>")]
 pub fn known_full_name(
     index_reader: &State<WindowsRefIndexReader>,
-    mut name_lookup: Json<FullPathLookup>
-) -> serde_json::Value {
+    name_lookup: Json<FullPathLookup>
+) -> Result<serde_json::Value, VanillaError> {
     let start = Instant::now();
 
-    name_lookup.value = name_lookup.value.trim_start_matches(r"\").to_string();
-    name_lookup.value = name_lookup.value.trim_start_matches(r"/").to_string();
-    name_lookup.value = RE_LETTER.replace(&name_lookup.value, "").to_string();
+    let hash = name_lookup.hash.clone();
 
     let name_lookup = name_lookup.into_inner()
-        .as_file_name_lookup()
-        .expect("Error converting FullPathLookup to FileNameLookup");
+        .as_file_name_lookup()?;
 
-    let result = known_lookup(&name_lookup, index_reader);
+    let mut result = known_lookup(&name_lookup, index_reader)?;
+
+    if let Some(hash) = hash {
+        let get_full_name = |path: &str| if path.is_empty() {
+            name_lookup.value.clone()
+        } else {
+            format!(r"{}\{}", path, name_lookup.value)
+        };
+
+        // The hash is checked at the path itself, then at the redirected
+        // paths. The first path the hash was seen at is the matched path.
+        let paths = name_lookup.get_lookup_paths()
+            .unwrap_or_default();
+        let mut check = None;
+        for path in &paths {
+            let path_check = index_reader.get_full_name_hash_check(&get_full_name(path), &hash, &name_lookup.filter)?;
+            if path_check.known_hash {
+                if name_lookup.redirect {
+                    result["MatchedPath"] = json!(get_indexed_directory(&name_lookup, path, index_reader)?);
+                }
+                check = Some(path_check);
+                break;
+            }
+            // Otherwise the seen hashes of the first known path are returned
+            let is_better = match &check {
+                Some(c) => !c.known_path && path_check.known_path,
+                None => true
+            };
+            if is_better {
+                check = Some(path_check);
+            }
+        }
+
+        if let Some(check) = check {
+            result["KnownHash"] = json!(check.known_hash);
+            if !check.known_hash {
+                result["SeenHashes"] = json!(check.seen_hashes);
+                result["SeenHashesTruncated"] = json!(check.seen_hashes_truncated);
+            }
+        }
+    }
 
     let duration = start.elapsed();
     info!("Time elapsed in known_full_name() is: {:?}", duration);
 
-    result
+    Ok(result)
 }
 
 
@@ -232,46 +296,145 @@ pub fn known_full_name(
 pub fn lookup_file_name(
     index_reader: &State<WindowsRefIndexReader>,
     mut name_lookup: Json<FileNameLookup>
-) -> serde_json::Value {
+) -> Result<serde_json::Value, VanillaError> {
     let start = Instant::now();
 
-    if let Some(path) = name_lookup.path.as_mut() {
-        *path = path.trim_start_matches(r"\").to_string();
-        *path = path.trim_start_matches(r"/").to_string();
-        *path = RE_LETTER.replace(&path, "").to_string();
-    }
-    let result = resolve("Name", &name_lookup, index_reader);
+    name_lookup.normalize_path();
+    let result = resolve(&name_lookup, index_reader)?;
 
     let duration = start.elapsed();
     info!("Time elapsed in lookup_file_name() is: {:?}", duration);
 
-    result
+    Ok(result)
 }
 
 
 #[post("/api/v1/lookup/fullname", format="json", data="<name_lookup>")]
 pub fn lookup_full_name(
     index_reader: &State<WindowsRefIndexReader>,
-    mut name_lookup: Json<FullPathLookup>
-) -> serde_json::Value {
+    name_lookup: Json<FullPathLookup>
+) -> Result<serde_json::Value, VanillaError> {
     let start = Instant::now();
 
-    name_lookup.value = name_lookup.value.trim_start_matches(r"/").to_string();
-    name_lookup.value = name_lookup.value.trim_start_matches(r"\").to_string();
-    name_lookup.value = RE_LETTER.replace(&name_lookup.value, "").to_string();
-
     let name_lookup = name_lookup.into_inner()
-        .as_file_name_lookup()
-        .expect("Error converting FullPathLookup to FileNameLookup");
+        .as_file_name_lookup()?;
 
     let result = resolve(
-        "Name",
         &name_lookup,
         index_reader
-    );
+    )?;
 
     let duration = start.elapsed();
     info!("Time elapsed in lookup_full_name() is: {:?}", duration);
 
-    result
+    Ok(result)
+}
+
+
+/// Classify a full path (and optional hash) as Vanilla, MasqueradeCandidate,
+/// HashMismatch or Unknown.
+#[post("/api/v1/verdict/fullname", format="json", data="<name_lookup>")]
+pub fn verdict_full_name(
+    index_reader: &State<WindowsRefIndexReader>,
+    name_lookup: Json<FullPathLookup>
+) -> Result<serde_json::Value, VanillaError> {
+    let start = Instant::now();
+
+    let hash = name_lookup.hash.clone();
+    let name_lookup = name_lookup.into_inner()
+        .as_file_name_lookup()?;
+
+    // Without redirect there is a single path. With redirect, the first path the
+    // file is Vanilla in is used, else the first path the name is known in.
+    // The name is looked up once and shared by every candidate path
+    let name_directories = get_name_directories(index_reader, &name_lookup.value, &name_lookup.filter)?;
+    let mut paths = name_lookup.get_lookup_paths()
+        .unwrap_or_default()
+        .into_iter();
+    let get_verdict = |path: &str| get_name_path_verdict(
+        index_reader,
+        &name_directories,
+        path,
+        &name_lookup.value,
+        hash.as_deref(),
+        &name_lookup.filter
+    );
+
+    let mut path = paths.next().unwrap_or_default();
+    let mut verdict = get_verdict(&path)?;
+    if verdict.known_name {
+        for other_path in paths {
+            if verdict.verdict == Verdict::Vanilla {
+                break;
+            }
+            let other_verdict = get_verdict(&other_path)?;
+            if other_verdict.verdict == Verdict::Vanilla || (!verdict.known_path && other_verdict.known_path) {
+                path = other_path;
+                verdict = other_verdict;
+            }
+        }
+    }
+
+    let mut result = json!(verdict);
+    if name_lookup.redirect {
+        // The known directories can be truncated, so the path is looked up
+        let matched_path = if verdict.known_path {
+            get_indexed_directory(&name_lookup, &path, index_reader)?
+        } else {
+            None
+        };
+        result["MatchedPath"] = json!(matched_path);
+    }
+    if !verdict.known_name {
+        add_similar_names(&mut result, &name_lookup.value, index_reader)?;
+    }
+
+    let duration = start.elapsed();
+    info!("Time elapsed in verdict_full_name() is: {:?}", duration);
+
+    Ok(result)
+}
+
+
+/// Every data set a full path (or a file name in any directory) exists in,
+/// ordered by build, with the first and last builds it was seen in.
+#[post("/api/v1/history/fullname", format="json", data="<name_lookup>")]
+pub fn history_full_name(
+    index_reader: &State<WindowsRefIndexReader>,
+    name_lookup: Json<FullPathLookup>
+) -> Result<serde_json::Value, VanillaError> {
+    let start = Instant::now();
+
+    let full_path = name_lookup.get_windows_path();
+    let history = index_reader.get_build_history(&full_path, &name_lookup.filter)?;
+
+    let duration = start.elapsed();
+    info!("Time elapsed in history_full_name() is: {:?}", duration);
+
+    Ok(json!(history))
+}
+
+
+/// The known names that look like a name (by edit distance and look-alike
+/// characters), best first.
+#[post("/api/v1/similar/name", format="json", data="<name_lookup>")]
+pub fn similar_file_name(
+    index_reader: &State<WindowsRefIndexReader>,
+    name_lookup: Json<SimilarNameLookup>
+) -> Result<serde_json::Value, VanillaError> {
+    let start = Instant::now();
+
+    let limit = name_lookup.limit.unwrap_or(DEFAULT_SIMILAR_NAME_LIMIT);
+    if limit > MAX_SIMILAR_NAME_LIMIT {
+        return Err(VanillaError::invalid_input(
+            format!("limit {} is greater than {}", limit, MAX_SIMILAR_NAME_LIMIT)
+        ));
+    }
+
+    let similar = index_reader.get_similar_names(&name_lookup.value, limit)?;
+
+    let duration = start.elapsed();
+    info!("Time elapsed in similar_file_name() is: {:?}", duration);
+
+    Ok(json!({"SimilarNames": similar}))
 }
@@ -0,0 +1,57 @@
+use std::time::Instant;
+use serde::{Serialize, Deserialize};
+use serde_json::json;
+use rocket::{post, State};
+use rocket::serde::json::Json;
+use crate::error::VanillaError;
+use crate::index::{check_search_window, LookupFilter, WindowsRefIndexReader};
+
+/// The number of hits of a page when no limit is given
+const DEFAULT_SEARCH_LIMIT: usize = 100;
+/// The max number of hits of a page
+const MAX_SEARCH_LIMIT: usize = 1000;
+
+
+#[derive(Deserialize, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct GlobSearch {
+    value: String,
+    limit: Option<usize>,
+    offset: Option<usize>,
+    #[serde(flatten)]
+    filter: LookupFilter
+}
+
+
+/// Search file names (e.g. `*svc*.dll`) or full paths (e.g.
+/// `C:\Windows\System32\drivers\*.sys`) with a glob. `limit` (at most 1000)
+/// and `offset` page through the hits, up to `offset + limit` of 10000.
+#[post("/api/v1/search", format="json", data="<search>")]
+pub fn search_glob(
+    index_reader: &State<WindowsRefIndexReader>,
+    search: Json<GlobSearch>
+) -> Result<serde_json::Value, VanillaError> {
+    let start = Instant::now();
+
+    let limit = search.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if limit > MAX_SEARCH_LIMIT {
+        return Err(VanillaError::invalid_input(
+            format!("limit {} is greater than {}", limit, MAX_SEARCH_LIMIT)
+        ));
+    }
+
+    let offset = search.offset.unwrap_or_default();
+    check_search_window(limit, offset)?;
+
+    let results = index_reader.search_glob(
+        &search.value,
+        &search.filter,
+        limit,
+        offset
+    )?;
+
+    let duration = start.elapsed();
+    info!("Time elapsed in search_glob() is: {:?}", duration);
+
+    Ok(json!(results))
+}
@@ -0,0 +1,244 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+
+/// The max edit distance (after confusable normalization) of a similar name
+pub const MAX_SIMILAR_DISTANCE: usize = 2;
+/// The min score of a similar name
+pub const MIN_SIMILAR_SCORE: f64 = 0.7;
+
+/// Characters that look like (or are commonly swapped for) ASCII characters.
+/// Names are lowercased before these are applied. 'i' and 'l' are not mapped to
+/// each other, since that makes common names such as `mail.dll` and `mall.dll`
+/// equal.
+const CONFUSABLES: &[(char, char)] = &[
+    // Digits and letters
+    ('0', 'o'), ('1', 'l'), ('|', 'l'), ('5', 's'), ('$', 's'), ('@', 'a'),
+    // Cyrillic
+    ('\u{0430}', 'a'), ('\u{0435}', 'e'), ('\u{043e}', 'o'), ('\u{0440}', 'p'),
+    ('\u{0441}', 'c'), ('\u{0443}', 'y'), ('\u{0445}', 'x'), ('\u{0455}', 's'),
+    ('\u{0456}', 'i'), ('\u{0458}', 'j'), ('\u{04bb}', 'h'), ('\u{0501}', 'd'),
+    // Greek
+    ('\u{03b1}', 'a'), ('\u{03bf}', 'o'), ('\u{03c1}', 'p'), ('\u{03bd}', 'v'),
+    ('\u{03b9}', 'i'), ('\u{03ba}', 'k'), ('\u{03c4}', 't'),
+    // Latin look-alikes
+    ('\u{0131}', 'i'), ('\u{0269}', 'i'), ('\u{0251}', 'a'), ('\u{0261}', 'g'),
+];
+
+/// Character sequences that look like a single character
+const CONFUSABLE_SEQUENCES: &[(&str, &str)] = &[
+    ("rn", "m"), ("vv", "w"),
+];
+
+
+/// Normalize a file name so that look-alike names are equal: the name is
+/// lowercased, whitespace and invisible characters (zero width, bidi controls)
+/// are removed, fullwidth forms are mapped to ASCII and confusable characters
+/// are mapped to the ASCII character they look like (e.g. `svch0st.exe` and
+/// `ѕvchost.exe` -> `svchost.exe`).
+pub fn normalize_confusables(name: &str) -> String {
+    let mut normalized: String = name.to_lowercase()
+        .chars()
+        .filter(|c| !c.is_whitespace() && !is_invisible(*c))
+        .map(|c| match c {
+            // Fullwidth ASCII
+            '\u{ff01}'..='\u{ff5e}' => char::from_u32(c as u32 - 0xfee0)
+                .unwrap_or(c)
+                .to_ascii_lowercase(),
+            c => c
+        })
+        .map(|c| CONFUSABLES.iter()
+            .find(|(from, _)| *from == c)
+            .map_or(c, |(_, to)| *to))
+        .collect();
+
+    for (from, to) in CONFUSABLE_SEQUENCES {
+        normalized = normalized.replace(from, to);
+    }
+    normalized
+}
+
+
+/// Zero width and bidi control characters that do not show up in a name
+fn is_invisible(c: char) -> bool {
+    matches!(c, '\u{200b}'..='\u{200f}' | '\u{202a}'..='\u{202e}' | '\u{2060}'..='\u{2064}' | '\u{feff}')
+}
+
+
+/// Get the edit distance (optimal string alignment) between two strings.
+/// Insertions, deletions, substitutions and transpositions of adjacent
+/// characters each count as one edit.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // Three rows of the distance matrix are enough for transpositions
+    let mut before: Vec<usize> = vec![0; b.len() + 1];
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        current[0] = i;
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            current[j] = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                current[j] = current[j].min(before[j - 2] + 1);
+            }
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    previous[b.len()]
+}
+
+
+/// A known name that looks like a name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SimilarName {
+    #[serde(rename = "Name")]
+    pub name: String,
+    /// From 0 to 1, where 1 is a look-alike that only differs by confusable
+    /// characters
+    #[serde(rename = "Score")]
+    pub score: f64,
+    /// The edit distance between the (lowercase) names
+    #[serde(rename = "Distance")]
+    pub distance: usize,
+    /// The names are equal after confusable normalization
+    #[serde(rename = "Confusable")]
+    pub confusable: bool,
+}
+
+
+/// Compare a name against a known name. None is returned when the names are
+/// equal (case insensitive) or not similar enough.
+pub fn get_similar_name(name: &str, known: &str) -> Option<SimilarName> {
+    let lower = name.to_lowercase();
+    let known_lower = known.to_lowercase();
+    let normalized = normalize_confusables(&lower);
+    let known_normalized = normalize_confusables(&known_lower);
+
+    compare_names(&lower, &normalized, &known_lower, &known_normalized)
+        .map(|similar| SimilarName { name: known.to_owned(), ..similar })
+}
+
+
+/// Compare the lowercase and normalized forms of a name and a known name (see
+/// `get_similar_name`). The SimilarName has the lowercase known name.
+fn compare_names(
+    lower: &str,
+    normalized: &str,
+    known_lower: &str,
+    known_normalized: &str
+) -> Option<SimilarName> {
+    if lower == known_lower {
+        return None;
+    }
+
+    let length = normalized.chars().count()
+        .max(known_normalized.chars().count());
+    let length_difference = normalized.chars().count()
+        .abs_diff(known_normalized.chars().count());
+    if length == 0 || length_difference > MAX_SIMILAR_DISTANCE {
+        return None;
+    }
+
+    let normalized_distance = edit_distance(normalized, known_normalized);
+    if normalized_distance > MAX_SIMILAR_DISTANCE {
+        return None;
+    }
+
+    let confusable = normalized_distance == 0;
+    let score = if confusable {
+        1.0
+    } else {
+        1.0 - normalized_distance as f64 / length as f64
+    };
+    if score < MIN_SIMILAR_SCORE {
+        return None;
+    }
+
+    Some( SimilarName {
+        name: known_lower.to_owned(),
+        score,
+        distance: edit_distance(lower, known_lower),
+        confusable
+    })
+}
+
+
+/// A known name in its lowercase and normalized forms.
+#[derive(Debug, Clone)]
+struct KnownName {
+    lower: String,
+    normalized: String,
+}
+
+
+/// Known names that are normalized once, so that looking up the names similar
+/// to a name does not normalize every known name again. The names are kept by
+/// the length of their normalized form and only the lengths within
+/// `MAX_SIMILAR_DISTANCE` of a name are compared.
+#[derive(Debug, Clone, Default)]
+pub struct SimilarNameIndex {
+    names: BTreeMap<usize, Vec<KnownName>>,
+    count: usize,
+}
+impl SimilarNameIndex {
+    /// Normalize the known names. Names are compared lowercase, so names that
+    /// only differ by case are kept once.
+    pub fn from_names<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>
+    {
+        let mut index = Self::default();
+        let mut lower_names: Vec<String> = names.into_iter()
+            .map(|n| n.as_ref().to_lowercase())
+            .collect();
+        lower_names.sort_unstable();
+        lower_names.dedup();
+
+        for lower in lower_names {
+            let normalized = normalize_confusables(&lower);
+            index.names.entry(normalized.chars().count())
+                .or_default()
+                .push(KnownName { lower, normalized });
+            index.count += 1;
+        }
+        index
+    }
+
+    /// The number of known names
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    /// Check if there are no known names
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Get the known names that look like a name, best first. The names are
+    /// lowercase.
+    pub fn get_similar_names(&self, name: &str, limit: usize) -> Vec<SimilarName> {
+        let lower = name.to_lowercase();
+        let normalized = normalize_confusables(&lower);
+        let length = normalized.chars().count();
+
+        let lengths = length.saturating_sub(MAX_SIMILAR_DISTANCE)..=length + MAX_SIMILAR_DISTANCE;
+        let mut similar: Vec<SimilarName> = self.names.range(lengths)
+            .flat_map(|(_, names)| names)
+            .filter_map(|known| compare_names(&lower, &normalized, &known.lower, &known.normalized))
+            .collect();
+
+        similar.sort_by(|a, b| b.score.total_cmp(&a.score)
+            .then_with(|| a.distance.cmp(&b.distance))
+            .then_with(|| a.name.cmp(&b.name)));
+        similar.truncate(limit);
+        similar
+    }
+}
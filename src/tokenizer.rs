@@ -7,6 +7,7 @@ use tantivy::tokenizer::BoxTokenStream;
 /// This tokenizer creates a single raw token for the entire value
 /// and uses lowercasing so that case insensitive searches can be
 /// performed.
+#[allow(clippy::empty_line_after_doc_comments)]
 #[derive(Clone)]
 pub struct RawLowerTokenizer;
 
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Write;
+use std::str::FromStr;
+use serde::Serialize;
+use crate::error::VanillaError;
+use crate::index::{LookupFilter, WindowsRefIndexReader};
+use crate::record::TriageRecord;
+use crate::verdict::{get_name_directories, get_name_path_verdict, NameDirectories, PathVerdict, Verdict};
+use crate::winpath::WindowsPath;
+
+/// The max number of file names whose directories are cached by a Triage
+const NAME_CACHE_LIMIT: usize = 100_000;
+
+/// The columns of a CSV triage report
+const CSV_REPORT_HEADER: &[&str] = &[
+    "FullName", "Length", "Hash", "Verdict", "KnownName", "KnownPath", "KnownHash", "KnownDirectories"
+];
+
+/// The verdict of a triaged file.
+#[derive(Debug, Clone, Serialize)]
+pub struct TriageResult {
+    #[serde(rename = "FullName")]
+    pub full_name: String,
+    #[serde(rename = "Length")]
+    pub length: Option<u64>,
+    /// The hash the verdict was based on
+    #[serde(rename = "Hash")]
+    pub hash: Option<String>,
+    #[serde(flatten)]
+    pub verdict: PathVerdict,
+}
+impl TriageResult {
+    /// The values of the result in the order of the CSV report header
+    fn csv_row(&self) -> Vec<String> {
+        let to_string = |v: Option<String>| v.unwrap_or_default();
+        vec![
+            self.full_name.clone(),
+            to_string(self.length.map(|v| v.to_string())),
+            to_string(self.hash.clone()),
+            format!("{:?}", self.verdict.verdict),
+            self.verdict.known_name.to_string(),
+            self.verdict.known_path.to_string(),
+            to_string(self.verdict.known_hash.map(|v| v.to_string())),
+            self.verdict.known_directories.join("|"),
+        ]
+    }
+}
+
+
+/// Checks host files against the index. Host listings have many files with
+/// the same name (e.g. `desktop.ini`), so the directories of each name are only
+/// looked up once.
+pub struct Triage<'a> {
+    index_reader: &'a WindowsRefIndexReader,
+    filter: LookupFilter,
+    /// lowercase name -> the directories it is known in
+    name_cache: HashMap<String, NameDirectories>,
+}
+impl<'a> Triage<'a> {
+    /// Only the builds matching `filter` are checked
+    pub fn new(index_reader: &'a WindowsRefIndexReader, filter: LookupFilter) -> Self {
+        Self {
+            index_reader,
+            filter,
+            name_cache: HashMap::new()
+        }
+    }
+
+    /// Check a host file against the index.
+    pub fn triage_record(&mut self, record: &TriageRecord) -> Result<TriageResult, VanillaError> {
+        let directory = WindowsPath::from_directory(&record.directory);
+        let hash = record.get_hash();
+
+        let key = record.name.to_lowercase();
+        if !self.name_cache.contains_key(&key) {
+            if self.name_cache.len() >= NAME_CACHE_LIMIT {
+                self.name_cache.clear();
+            }
+            let name_directories = get_name_directories(self.index_reader, &record.name, &self.filter)?;
+            self.name_cache.insert(key.clone(), name_directories);
+        }
+
+        let verdict = get_name_path_verdict(
+            self.index_reader,
+            &self.name_cache[&key],
+            directory.relative_path(),
+            &record.name,
+            hash,
+            &self.filter
+        )?;
+
+        Ok( TriageResult {
+            full_name: record.full_name.clone(),
+            length: record.length,
+            hash: hash.map(|h| h.to_owned()),
+            verdict
+        })
+    }
+}
+
+
+/// The number of files per verdict of a triage.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TriageSummary {
+    #[serde(rename = "Vanilla")]
+    pub vanilla: usize,
+    #[serde(rename = "MasqueradeCandidate")]
+    pub masquerade_candidate: usize,
+    #[serde(rename = "HashMismatch")]
+    pub hash_mismatch: usize,
+    #[serde(rename = "Unknown")]
+    pub unknown: usize,
+    /// Records that could not be read or checked
+    #[serde(rename = "Errors")]
+    pub errors: usize,
+}
+impl TriageSummary {
+    /// Count a verdict
+    pub fn add(&mut self, verdict: Verdict) {
+        match verdict {
+            Verdict::Vanilla => self.vanilla += 1,
+            Verdict::MasqueradeCandidate => self.masquerade_candidate += 1,
+            Verdict::HashMismatch => self.hash_mismatch += 1,
+            Verdict::Unknown => self.unknown += 1,
+        }
+    }
+}
+
+
+/// The output format of a triage report.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Jsonl,
+    Csv,
+}
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "jsonl" => Ok(Self::Jsonl),
+            "csv" => Ok(Self::Csv),
+            other => Err(format!("Unknown report format '{}'", other))
+        }
+    }
+}
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Jsonl => write!(f, "jsonl"),
+            Self::Csv => write!(f, "csv"),
+        }
+    }
+}
+
+
+/// Writes triage results in a ReportFormat.
+pub enum ReportWriter<W: Write> {
+    Jsonl(W),
+    Csv(Box<csv::Writer<W>>),
+}
+impl<W: Write> ReportWriter<W> {
+    /// Create a ReportWriter. For CSV the header is written right away.
+    pub fn new(writer: W, format: ReportFormat) -> Result<Self, VanillaError> {
+        match format {
+            ReportFormat::Jsonl => Ok(Self::Jsonl(writer)),
+            ReportFormat::Csv => {
+                let mut csv_writer = csv::Writer::from_writer(writer);
+                csv_writer.write_record(CSV_REPORT_HEADER)
+                    .map_err(|e| VanillaError::from_message(format!("{:?}", e)))?;
+                Ok(Self::Csv(Box::new(csv_writer)))
+            }
+        }
+    }
+
+    /// Write a single result
+    pub fn write_result(&mut self, result: &TriageResult) -> Result<(), VanillaError> {
+        match self {
+            Self::Jsonl(writer) => {
+                let line = serde_json::to_string(result)
+                    .map_err(|e| VanillaError::from_message(format!("{:?}", e)))?;
+                writeln!(writer, "{}", line)?;
+            },
+            Self::Csv(writer) => {
+                writer.write_record(result.csv_row())
+                    .map_err(|e| VanillaError::from_message(format!("{:?}", e)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer
+    pub fn flush(&mut self) -> Result<(), VanillaError> {
+        match self {
+            Self::Jsonl(writer) => writer.flush()?,
+            Self::Csv(writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+}
@@ -1,27 +1,56 @@
+use std::fmt;
 use std::fs::File;
 use std::io::prelude::*;
+use std::str::FromStr;
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
 use walkdir::WalkDir;
+use sha2::{Digest, Sha256};
 use lazy_static::lazy_static;
 use regex::Regex;
-use serde::Serialize;
+use serde::{Serialize, Serializer};
 use serde_json::{json, Value};
 use encoding::{Encoding, DecoderTrap};
 use encoding::all::{UTF_16LE, UTF_8};
+use crate::winpath::WindowsPath;
 
 
-/// To save a little bit of room, we dont care to index these fields
-const EXLCUDE_LIST: &'static [&'static str] = &[
+/// To save a little bit of room, these fields are left out of the JSON records
+/// by default
+pub const DEFAULT_EXCLUDE_LIST: &[&str] = &[
     "LastAccessTimeUtc",
     "LastWriteTimeUtc",
     "Sddl"
 ];
 
+/// Columns a file list needs to be read into VanillaFileRecords
+const REQUIRED_COLUMNS: &[&str] = &[
+    "DirectoryName", "Name", "FullName", "Length", "Attributes"
+];
+
+/// Timestamp formats found in the file lists
+const TIMESTAMP_FORMATS: &[&str] = &[
+    "%m/%d/%Y %I:%M:%S %p",
+    "%m/%d/%Y %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S%.fZ",
+    "%Y-%m-%d %H:%M:%S%.f",
+];
+
 
 lazy_static! {
-    /// Regexs for parsing values in the SystemInfo_ files
-    static ref RE_OS_NAME: Regex = Regex::new(r"(?m)^OS Name:\s*([^\s].+?)\r?$").unwrap();
-    static ref RE_OS_VERSION: Regex = Regex::new(r"(?m)^OS Version:\s*([^\s].+?)\r?$").unwrap();
+    /// Regexs for parsing lines in the SystemInfo_ files
+    static ref RE_KEY_VALUE: Regex = Regex::new(r"^([^:]+):\s+(.*)$").unwrap();
+    static ref RE_LIST_ITEM: Regex = Regex::new(r"^\[\d+\]:\s*(.+)$").unwrap();
+    /// Regexs for getting the build number from the OS Version
+    static ref RE_BUILD: Regex = Regex::new(r"(?i)\bBuild\s+(\d+)").unwrap();
+    static ref RE_VERSION_BUILD: Regex = Regex::new(r"^\d+\.\d+\.(\d+)").unwrap();
+    /// Regex for data set folder names such as `W10_20H2_Pro_19042.631`,
+    /// `W11_21H2_Pro_ARM_22000.376` (the architecture after the edition) or
+    /// `W10_1507_Pro_20150729_10240` (the date the data set was collected)
+    static ref RE_DATA_SET_NAME: Regex = Regex::new(
+        r"^[[:alnum:]]+_(\d{4}|\d{2}H\d)_(.+?)(?:_((?i:arm64|arm|x64|x86)))?(?:_\d{8})?_(\d{4,5}(?:\.\d+)?)$"
+    ).unwrap();
 }
 
 
@@ -46,6 +75,87 @@ pub fn get_system_info_files(path: impl AsRef<Path>) -> Vec<PathBuf> {
 }
 
 
+/// Get the path of a data set folder relative to the VanillaWindowsReference root
+/// with `/` separators (e.g. `Windows10/20H2/W10_20H2_Pro_19042.631`). If the root
+/// is the data set folder itself, the folder name is used.
+pub fn get_data_set_path(root: impl AsRef<Path>, location: impl AsRef<Path>) -> String {
+    let location = location.as_ref();
+    let relative = location.strip_prefix(root.as_ref())
+        .unwrap_or(location);
+
+    let path = relative.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    if path.is_empty() {
+        location.file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default()
+    } else {
+        path
+    }
+}
+
+
+/// Where a file list comes from. The values are parsed from the data set
+/// folder name, such as `W10_20H2_Pro_19042.631`, and are None when the name
+/// does not follow the repo's naming.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct DataSetInfo {
+    /// The data set folder relative to the reference root (see `get_data_set_path`)
+    #[serde(rename = "DataSetPath")]
+    pub path: String,
+    /// e.g. `Pro` or `Enterprise`
+    #[serde(rename = "Edition")]
+    pub edition: Option<String>,
+    /// e.g. `ARM`, the repo's folder names only have it for non x64 data sets
+    /// so it is `x64` when the name has none
+    #[serde(rename = "Architecture")]
+    pub architecture: Option<String>,
+    /// e.g. `1507` or `20H2`
+    #[serde(rename = "Release")]
+    pub release: Option<String>,
+    /// The build including the UBR when the folder has it (e.g. `19042.631`)
+    #[serde(rename = "FullBuild")]
+    pub full_build: Option<String>,
+}
+impl DataSetInfo {
+    /// Create the info of a data set folder in the reference root
+    pub fn new(root: impl AsRef<Path>, location: impl AsRef<Path>) -> Self {
+        let path = get_data_set_path(root, location);
+        let name = path.rsplit('/')
+            .next()
+            .unwrap_or_default();
+
+        match RE_DATA_SET_NAME.captures(name) {
+            Some(captures) => Self {
+                release: Some(captures[1].to_owned()),
+                edition: Some(captures[2].to_owned()),
+                architecture: Some(captures.get(3)
+                    .map_or("x64", |m| m.as_str())
+                    .to_owned()),
+                full_build: Some(captures[4].to_owned()),
+                path
+            },
+            None => Self {
+                path,
+                ..Default::default()
+            }
+        }
+    }
+
+    /// Add the values to a JSON record
+    pub fn add_to_value(&self, value: &mut Value) {
+        value["DataSetPath"] = json!(self.path);
+        value["Edition"] = json!(self.edition);
+        value["Architecture"] = json!(self.architecture);
+        value["Release"] = json!(self.release);
+        value["FullBuild"] = json!(self.full_build);
+    }
+}
+
+
 /// Search a folder for csv files and return the found paths as PathBufs.
 fn get_csv_files(path: impl AsRef<Path>) -> Vec<PathBuf> {
     let mut paths = Vec::new();
@@ -67,9 +177,44 @@ fn get_csv_files(path: impl AsRef<Path>) -> Vec<PathBuf> {
 #[derive(Debug)]
 pub struct WindowsFileList {
     system_info_path: PathBuf,
-    file_list_path: PathBuf
+    file_list_path: PathBuf,
+    data_set: DataSetInfo
 }
 impl WindowsFileList {
+    /// Where the file list comes from
+    pub fn data_set(&self) -> &DataSetInfo {
+        &self.data_set
+    }
+
+    /// The path of the SystemInfo_ file
+    pub fn system_info_path(&self) -> &Path {
+        &self.system_info_path
+    }
+
+    /// The path of the CSV file list
+    pub fn file_list_path(&self) -> &Path {
+        &self.file_list_path
+    }
+
+    /// Get a SHA256 (hex) of the SystemInfo_ and CSV file content. This is used
+    /// to tell if a data set changed since it was indexed.
+    pub fn get_content_hash(&self) -> Result<String, String> {
+        let mut hasher = Sha256::new();
+        for path in [&self.system_info_path, &self.file_list_path] {
+            let mut fh = File::open(path)
+                .map_err(|e| format!("Could not open '{}'. {:?}", path.to_string_lossy(), e))?;
+            std::io::copy(&mut fh, &mut hasher)
+                .map_err(|e| format!("Error hashing '{}'. {:?}", path.to_string_lossy(), e))?;
+        }
+
+        Ok(
+            hasher.finalize()
+                .iter()
+                .map(|b| format!("{:02x}", b))
+                .collect()
+        )
+    }
+
     /// Create a WindowsFileList from a folder. Errors out if SystemInfo_ and CSV
     /// file can't be found.
     pub fn from_folder(path: impl AsRef<Path>) -> Result<Self, String> {
@@ -103,19 +248,33 @@ impl WindowsFileList {
 
         Ok( WindowsFileList{
             system_info_path,
-            file_list_path
+            file_list_path,
+            data_set: DataSetInfo::new(path, path)
         })
     }
 
+    /// Create a WindowsFileList from a data set folder in the reference root.
+    /// Unlike `from_folder`, the DataSetPath is relative to the root.
+    pub fn from_data_set(
+        root: impl AsRef<Path>,
+        path: impl AsRef<Path>
+    ) -> Result<Self, String> {
+        let mut file_list = Self::from_folder(&path)?;
+        file_list.data_set = DataSetInfo::new(root, path);
+        Ok(file_list)
+    }
+
     /// Get a WinFileListRecordIterator based off of the SystemInfo_/CSV
-    /// pair. The iterator adds fields from the SystemInfo_ to the CSV values.
+    /// pair. The iterator adds fields from the SystemInfo_ and the DataSetInfo to
+    /// the CSV values.
     pub fn into_iter(&self) -> Result<WinFileListRecordIterator, String> {
         // Get the Windows info from the SystemInfo_ file
-        let win_info = json!(WindowsInfo::from_path(&self.system_info_path)?);
+        let mut win_info = json!(WindowsInfo::from_path(&self.system_info_path)?);
+        self.data_set.add_to_value(&mut win_info);
         // Create CSV reader for the file list
         let mut csv_rdr = csv::ReaderBuilder::new()
             .delimiter(b',')
-            .from_path(&self.file_list_path.as_path())
+            .from_path(self.file_list_path.as_path())
             .map_err(|e|format!("{:?}", e))?;
         // Generate a header list
         let header: Vec<String> = csv_rdr.headers()
@@ -127,9 +286,24 @@ impl WindowsFileList {
         Ok( WinFileListRecordIterator {
             win_info,
             header,
+            exclude: DEFAULT_EXCLUDE_LIST.iter()
+                .map(|c| c.to_string())
+                .collect(),
             reader: csv_rdr
         })
     }
+
+    /// Get a VanillaFileRecordIterator based off of the SystemInfo_/CSV pair.
+    /// Errors out if a required column is missing from the CSV header.
+    pub fn into_record_iter(&self) -> Result<VanillaFileRecordIterator, String> {
+        let win_info = WindowsInfo::from_path(&self.system_info_path)?;
+        let records = CsvFileRecordIterator::from_path(&self.file_list_path)?;
+
+        Ok( VanillaFileRecordIterator {
+            win_info,
+            records
+        })
+    }
 }
 
 
@@ -137,9 +311,17 @@ impl WindowsFileList {
 pub struct WinFileListRecordIterator {
     pub win_info: Value,
     pub header: Vec<String>,
+    /// CSV columns that are left out of the records
+    exclude: Vec<String>,
     reader: csv::Reader<File>
 }
 impl WinFileListRecordIterator {
+    /// Set the CSV columns that are left out of the records. Defaults to
+    /// DEFAULT_EXCLUDE_LIST.
+    pub fn set_exclude_list(&mut self, exclude: Vec<String>) {
+        self.exclude = exclude;
+    }
+
     /// Helper function to get headers from not just the CSV file, but also the Windows Info
     pub fn get_headers(&self) -> Vec<String>{
         let value = self.win_info.clone();
@@ -148,11 +330,7 @@ impl WinFileListRecordIterator {
             .keys()
             .map(|v|v.to_string())
             .collect();
-        let c2 = self.header.iter()
-            .map(|v|v.clone())
-            .collect::<Vec<String>>();
-
-        columns.extend(c2);
+        columns.extend(self.header.iter().cloned());
         columns
     }
 }
@@ -160,28 +338,24 @@ impl Iterator for WinFileListRecordIterator {
     type Item = Value;
 
     fn next(&mut self) -> Option<Self::Item> {
-        loop {
-            if let Some(result) = self.reader.records().next() {
-                let record = match result {
-                    Ok(r) => r,
-                    Err(e) => {
-                        eprintln!("{:?}", e);
-                        continue;
-                    }
-                };
+        while let Some(result) = self.reader.records().next() {
+            let record = match result {
+                Ok(r) => r,
+                Err(e) => {
+                    eprintln!("{:?}", e);
+                    continue;
+                }
+            };
 
-                let mut value = self.win_info.clone();
-                for (i, column) in self.header.iter().enumerate() {
-                    if EXLCUDE_LIST.contains(&column.as_str()) {
-                        continue;
-                    }
-                    value[column] = json!(&record[i]);
+            let mut value = self.win_info.clone();
+            for (i, column) in self.header.iter().enumerate() {
+                if self.exclude.contains(column) {
+                    continue;
                 }
-                
-                return Some(value);
-            } else {
-                break
+                value[column] = json!(&record[i]);
             }
+
+            return Some(value);
         }
 
         None
@@ -189,17 +363,377 @@ impl Iterator for WinFileListRecordIterator {
 }
 
 
-/// Structure that represents the SystemInfo_ file. To add more fields to the output
-/// add them to this struct and parse accordingly.
-#[derive(Debug, Serialize)]
-pub struct WindowsInfo {
-    #[serde(rename = "OsName")]
+/// Attribute flags of a file as written by powershell (`System.IO.FileAttributes`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct FileAttributes(pub u32);
+impl FileAttributes {
+    pub const READ_ONLY: u32 = 0x1;
+    pub const HIDDEN: u32 = 0x2;
+    pub const SYSTEM: u32 = 0x4;
+    pub const DIRECTORY: u32 = 0x10;
+    pub const ARCHIVE: u32 = 0x20;
+    pub const DEVICE: u32 = 0x40;
+    pub const NORMAL: u32 = 0x80;
+    pub const TEMPORARY: u32 = 0x100;
+    pub const SPARSE_FILE: u32 = 0x200;
+    pub const REPARSE_POINT: u32 = 0x400;
+    pub const COMPRESSED: u32 = 0x800;
+    pub const OFFLINE: u32 = 0x1000;
+    pub const NOT_CONTENT_INDEXED: u32 = 0x2000;
+    pub const ENCRYPTED: u32 = 0x4000;
+    pub const INTEGRITY_STREAM: u32 = 0x8000;
+    pub const NO_SCRUB_DATA: u32 = 0x20000;
+
+    /// The .NET names of each flag
+    const NAMES: &'static [(&'static str, u32)] = &[
+        ("ReadOnly", Self::READ_ONLY),
+        ("Hidden", Self::HIDDEN),
+        ("System", Self::SYSTEM),
+        ("Directory", Self::DIRECTORY),
+        ("Archive", Self::ARCHIVE),
+        ("Device", Self::DEVICE),
+        ("Normal", Self::NORMAL),
+        ("Temporary", Self::TEMPORARY),
+        ("SparseFile", Self::SPARSE_FILE),
+        ("ReparsePoint", Self::REPARSE_POINT),
+        ("Compressed", Self::COMPRESSED),
+        ("Offline", Self::OFFLINE),
+        ("NotContentIndexed", Self::NOT_CONTENT_INDEXED),
+        ("Encrypted", Self::ENCRYPTED),
+        ("IntegrityStream", Self::INTEGRITY_STREAM),
+        ("NoScrubData", Self::NO_SCRUB_DATA),
+    ];
+
+    /// Check if all bits of `flag` are set.
+    pub fn contains(&self, flag: u32) -> bool {
+        self.0 & flag == flag
+    }
+}
+impl FromStr for FileAttributes {
+    type Err = String;
+
+    /// Parse the `Hidden, System, Archive` format. When not all bits have a name
+    /// .NET writes the numeric value instead, so that is handled as well.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Ok(Self::default());
+        }
+        if let Ok(value) = s.parse::<u32>() {
+            return Ok(Self(value));
+        }
+
+        let mut value = 0;
+        for name in s.split(',').map(|n| n.trim()) {
+            let (_, flag) = Self::NAMES.iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("Unknown file attribute '{}'", name))?;
+            value |= flag;
+        }
+
+        Ok(Self(value))
+    }
+}
+impl fmt::Display for FileAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let named = Self::NAMES.iter()
+            .fold(0, |acc, (_, flag)| acc | flag);
+        if self.0 & !named != 0 {
+            return write!(f, "{}", self.0);
+        }
+
+        let names = Self::NAMES.iter()
+            .filter(|(_, flag)| self.contains(*flag))
+            .map(|(name, _)| *name)
+            .collect::<Vec<&str>>();
+        write!(f, "{}", names.join(", "))
+    }
+}
+impl Serialize for FileAttributes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+
+/// Parse the timestamp format powershell uses when exporting to csv
+/// (e.g. `7/10/2015 11:00:41 AM`).
+pub fn parse_timestamp(value: &str) -> Result<DateTime<Utc>, String> {
+    let value = value.trim();
+    for format in TIMESTAMP_FORMATS {
+        if let Ok(dt) = NaiveDateTime::parse_from_str(value, format) {
+            return Ok(Utc.from_utc_datetime(&dt));
+        }
+    }
+    Err(format!("Unable to parse timestamp '{}'", value))
+}
+
+
+/// A strongly typed row of a file list.
+#[derive(Debug, Clone, Serialize)]
+pub struct VanillaFileRecord {
+    /// Paths are parsed when the row is read (see `WindowsPath`)
+    #[serde(rename = "DirectoryName")]
+    pub directory_name: WindowsPath,
+    #[serde(rename = "Name")]
     pub name: String,
-    #[serde(rename = "OsVersion")]
-    pub version: String,
+    #[serde(rename = "FullName")]
+    pub full_name: WindowsPath,
+    #[serde(rename = "Length")]
+    pub length: u64,
+    /// Timestamps are None when missing or in a format that is not handled (such
+    /// as the ones of non US locales)
+    #[serde(rename = "CreationTimeUtc")]
+    pub creation_time: Option<DateTime<Utc>>,
+    #[serde(rename = "LastAccessTimeUtc")]
+    pub last_access_time: Option<DateTime<Utc>>,
+    #[serde(rename = "LastWriteTimeUtc")]
+    pub last_write_time: Option<DateTime<Utc>>,
+    #[serde(rename = "Attributes")]
+    pub attributes: FileAttributes,
+    /// Hashes are empty for files that could not be read
+    #[serde(rename = "MD5")]
+    pub md5: Option<String>,
+    #[serde(rename = "SHA256")]
+    pub sha256: Option<String>,
+    #[serde(rename = "Sddl")]
+    pub sddl: Option<String>,
 }
-impl WindowsInfo {
-    /// Create this structure from a SystemInfo_ file path.
+impl VanillaFileRecord {
+    /// Create a record from a csv row
+    fn from_csv_row(row: &CsvRow) -> Result<Self, String> {
+        let optional = |name: &str| -> Option<String> {
+            row.optional(name).map(|v| v.to_owned())
+        };
+        // A timestamp that can't be parsed does not fail the row
+        let timestamp = |name: &str| -> Option<DateTime<Utc>> {
+            row.optional(name)
+                .and_then(|v| parse_timestamp(v).ok())
+        };
+
+        let length = row.required("Length")?;
+        let length = length.trim()
+            .parse::<u64>()
+            .map_err(|e| format!("Invalid Length '{}': {}", length, e))?;
+
+        let attributes = row.required("Attributes")?
+            .parse::<FileAttributes>()?;
+
+        Ok( Self {
+            directory_name: WindowsPath::from_directory(row.required("DirectoryName")?),
+            name: row.required("Name")?.to_owned(),
+            full_name: WindowsPath::new(row.required("FullName")?),
+            length,
+            creation_time: timestamp("CreationTimeUtc"),
+            last_access_time: timestamp("LastAccessTimeUtc"),
+            last_write_time: timestamp("LastWriteTimeUtc"),
+            attributes,
+            md5: optional("MD5"),
+            sha256: optional("SHA256"),
+            sddl: optional("Sddl"),
+        })
+    }
+}
+
+
+/// A row of a CSV file whose values are looked up by column name.
+pub struct CsvRow<'a> {
+    record: &'a csv::StringRecord,
+    columns: &'a HashMap<String, usize>
+}
+impl<'a> CsvRow<'a> {
+    /// The value of a column. None if the column is not in the header (or the
+    /// row is short).
+    pub fn get(&self, name: &str) -> Option<&'a str> {
+        self.columns.get(name)
+            .and_then(|i| self.record.get(*i))
+    }
+
+    /// The value of a column, which must be in the row
+    pub fn required(&self, name: &str) -> Result<&'a str, String> {
+        self.get(name).ok_or_else(|| format!("Missing value for {}", name))
+    }
+
+    /// The trimmed value of a column. None if it is missing or empty.
+    pub fn optional(&self, name: &str) -> Option<&'a str> {
+        self.get(name)
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+    }
+}
+
+
+/// Reads the rows of a CSV file with a header, counting them so that errors
+/// contain the (1 based) row number, not counting the header.
+pub struct CsvColumnReader {
+    columns: HashMap<String, usize>,
+    reader: csv::Reader<File>,
+    row: usize
+}
+impl CsvColumnReader {
+    /// Open a CSV file. Errors out if one of the `required` columns is missing
+    /// from the CSV header.
+    pub fn from_path(path: impl AsRef<Path>, required: &[&str]) -> Result<Self, String> {
+        let path = path.as_ref();
+        let mut csv_rdr = csv::ReaderBuilder::new()
+            .delimiter(b',')
+            .from_path(path)
+            .map_err(|e|format!("{:?}", e))?;
+
+        let columns: HashMap<String, usize> = csv_rdr.headers()
+            .map_err(|e| format!("{:?}", e))?
+            .iter()
+            .enumerate()
+            .map(|(i, v)| (v.to_string(), i))
+            .collect();
+
+        for column in required {
+            if !columns.contains_key(*column) {
+                return Err(format!(
+                    "Column {} is missing from {}",
+                    column,
+                    path.to_string_lossy()
+                ));
+            }
+        }
+
+        Ok( Self {
+            columns,
+            reader: csv_rdr,
+            row: 0
+        })
+    }
+
+    /// Read the next row and convert it with `parse`. None at the end of the
+    /// file.
+    pub fn next_row<T>(
+        &mut self,
+        parse: impl FnOnce(&CsvRow) -> Result<T, String>
+    ) -> Option<Result<T, String>> {
+        let mut record = csv::StringRecord::new();
+        let result = self.reader.read_record(&mut record);
+        self.row += 1;
+
+        match result {
+            Ok(true) => {
+                let row = CsvRow { record: &record, columns: &self.columns };
+                Some(parse(&row).map_err(|e| format!("row {}: {}", self.row, e)))
+            },
+            Ok(false) => None,
+            Err(e) => Some(Err(format!("row {}: {:?}", self.row, e)))
+        }
+    }
+}
+
+
+/// This iterator reads typed records from a CSV file list on its own, such as a
+/// listing collected from a host with the same powershell command. Malformed rows
+/// are returned as errors that contain the (1 based) row number, not counting the
+/// header.
+pub struct CsvFileRecordIterator {
+    rows: CsvColumnReader
+}
+impl CsvFileRecordIterator {
+    /// Open a CSV file list. Errors out if a required column is missing from the
+    /// CSV header.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        Ok( Self {
+            rows: CsvColumnReader::from_path(path, REQUIRED_COLUMNS)?
+        })
+    }
+}
+impl Iterator for CsvFileRecordIterator {
+    type Item = Result<VanillaFileRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next_row(VanillaFileRecord::from_csv_row)
+    }
+}
+
+
+/// This iterator reads from CSV file and returns typed records. This is the typed
+/// counterpart to WinFileListRecordIterator. Malformed rows are returned as errors
+/// that contain the (1 based) row number, not counting the header.
+pub struct VanillaFileRecordIterator {
+    pub win_info: WindowsInfo,
+    records: CsvFileRecordIterator
+}
+impl Iterator for VanillaFileRecordIterator {
+    type Item = Result<VanillaFileRecord, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.records.next()
+    }
+}
+
+
+/// A single `Key: Value` entry of a SystemInfo_ file. Indented lines that follow
+/// an entry (such as the KBs listed under `Hotfix(s)` or the adapter details under
+/// `Network Card(s)`) are kept, trimmed, in `continuation`.
+#[derive(Debug, Clone)]
+pub struct SystemInfoEntry {
+    pub key: String,
+    pub value: String,
+    pub continuation: Vec<String>,
+}
+impl SystemInfoEntry {
+    /// Get the values of `[01]: value` style list items found in the continuation
+    /// lines of this entry.
+    pub fn list_items(&self) -> Vec<String> {
+        self.continuation.iter()
+            .filter_map(|line| RE_LIST_ITEM.captures(line))
+            .filter_map(|c| c.get(1))
+            .map(|m| m.as_str().to_owned())
+            .collect()
+    }
+}
+
+
+/// Line oriented representation of the `systeminfo` output stored in the
+/// SystemInfo_ files.
+#[derive(Debug, Default)]
+pub struct SystemInfo {
+    entries: Vec<SystemInfoEntry>
+}
+impl SystemInfo {
+    /// Parse the content of a SystemInfo_ file.
+    ///
+    /// `systeminfo` pads every key so that values start in the same column and
+    /// indents continuation lines to that column. The column is detected from the
+    /// continuation lines so that keys containing colons (`Virtual Memory: Max Size`)
+    /// are split correctly.
+    pub fn parse(content: &str) -> Self {
+        let value_column = get_value_column(content);
+
+        let mut entries: Vec<SystemInfoEntry> = Vec::new();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if line.starts_with(char::is_whitespace) {
+                // Continuation of the previous entry
+                if let Some(entry) = entries.last_mut() {
+                    entry.continuation.push(line.trim().to_owned());
+                }
+                continue;
+            }
+
+            if let Some((key, value)) = split_key_value(line, value_column) {
+                entries.push(SystemInfoEntry {
+                    key,
+                    value,
+                    continuation: Vec::new()
+                });
+            }
+        }
+
+        Self { entries }
+    }
+
+    /// Read and parse a SystemInfo_ file. Handles both the utf16 and utf8
+    /// encodings that powershell can produce.
     pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
         let path = path.as_ref();
 
@@ -217,7 +751,7 @@ impl WindowsInfo {
         }
 
         // Open the file from a path
-        let mut fh = File::open(&path)
+        let mut fh = File::open(path)
             .map_err(|e|format!(
                 "Could not open '{}'. {:?}",
                 path.to_string_lossy(),
@@ -232,44 +766,163 @@ impl WindowsInfo {
         ))?;
 
         // Some powershell output is in utf16, some is in utf8
-        let content = if &buffer[0..2] == &[0xff, 0xfe] {
+        let content = if buffer.starts_with(&[0xff, 0xfe]) {
             match UTF_16LE.decode(&buffer, DecoderTrap::Ignore) {
-                Ok(s) => s.to_string(),
+                Ok(s) => s,
                 Err(e) => {
                     return Err(format!("Error decoding utf16le: {:?}", e));
                 }
             }
         } else {
             match UTF_8.decode(&buffer, DecoderTrap::Ignore) {
-                Ok(s) => s.to_string(),
+                Ok(s) => s,
                 Err(e) => {
                     return Err(format!("Error decoding utf8: {:?}", e));
                 }
             }
         };
 
-        // Search for needed variables
-        let name = RE_OS_NAME.captures(&content)
-            .ok_or(format!(
-                "Unable to parse OS Name for '{}'", path.to_string_lossy()
-            ))?
-            .get(1)
-            .expect("Name group not valid.")
-            .as_str()
-            .to_owned();
-        let version = RE_OS_VERSION.captures(&content)
-            .ok_or(format!(
-                "Unable to parse OS Version for '{}'", path.to_string_lossy()
-            ))?
-            .get(1)
-            .expect("Version group not valid.")
-            .as_str()
-            .to_owned();
+        // Strip a byte order mark if one was decoded
+        Ok(Self::parse(content.trim_start_matches('\u{feff}')))
+    }
+
+    /// All entries in the order they appear in the file.
+    pub fn entries(&self) -> &[SystemInfoEntry] {
+        &self.entries
+    }
+
+    /// Get the entry for a key (case insensitive).
+    pub fn get(&self, key: &str) -> Option<&SystemInfoEntry> {
+        self.entries.iter()
+            .find(|e| e.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Get the non empty value for a key.
+    pub fn get_value(&self, key: &str) -> Option<&str> {
+        self.get(key)
+            .map(|e| e.value.as_str())
+            .filter(|v| !v.is_empty())
+    }
+}
+
+
+/// Get the column values start at. This is the smallest indentation of the
+/// continuation lines (nested blocks such as Network Card(s) indent further).
+fn get_value_column(content: &str) -> Option<usize> {
+    content.lines()
+        .filter(|line| !line.trim().is_empty() && line.starts_with(char::is_whitespace))
+        .map(|line| line.chars().take_while(|c| c.is_whitespace()).count())
+        .min()
+}
+
+
+/// Split a `Key:   Value` line.
+fn split_key_value(line: &str, value_column: Option<usize>) -> Option<(String, String)> {
+    // Split at the value column when the line is padded up to it
+    if let Some(column) = value_column {
+        if let Some((offset, _)) = line.char_indices().nth(column) {
+            let (key, value) = line.split_at(offset);
+            if key.ends_with(char::is_whitespace) {
+                let key = key.trim_end();
+                if let Some(key) = key.strip_suffix(':') {
+                    return Some((key.trim().to_owned(), value.trim().to_owned()));
+                }
+            }
+        }
+    }
+
+    // Keys without a value (e.g. `Registered Organization:`)
+    if let Some(key) = line.strip_suffix(':') {
+        return Some((key.trim().to_owned(), String::new()));
+    }
+
+    // Fall back to the first colon followed by whitespace
+    RE_KEY_VALUE.captures(line)
+        .map(|c| (c[1].trim().to_owned(), c[2].trim().to_owned()))
+}
+
+
+/// Get the build number from an OS Version such as `10.0.19042 N/A Build 19042`.
+pub fn parse_build_number(version: &str) -> Option<u64> {
+    RE_BUILD.captures(version)
+        .or_else(|| RE_VERSION_BUILD.captures(version))
+        .and_then(|c| c[1].parse::<u64>().ok())
+}
+
+
+/// Structure that represents the SystemInfo_ file. To add more fields to the output
+/// add them to this struct and parse accordingly.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowsInfo {
+    #[serde(rename = "OsName")]
+    pub name: String,
+    #[serde(rename = "OsVersion")]
+    pub version: String,
+    /// The build number from the OS Version (e.g. `19042`)
+    #[serde(rename = "OsBuild")]
+    pub build: Option<u64>,
+    /// e.g. `Multiprocessor Free`
+    #[serde(rename = "OsBuildType")]
+    pub build_type: Option<String>,
+    /// e.g. `x64-based PC` or `ARM64-based PC`
+    #[serde(rename = "SystemType")]
+    pub system_type: Option<String>,
+    /// As written by systeminfo, which is in the local time of the system.
+    #[serde(rename = "OriginalInstallDate")]
+    pub original_install_date: Option<String>,
+    /// The locale identifier of the System Locale (e.g. `en-us`)
+    #[serde(rename = "SystemLocale")]
+    pub system_locale: Option<String>,
+    #[serde(rename = "TimeZone")]
+    pub time_zone: Option<String>,
+    /// The installed KBs listed under Hotfix(s)
+    #[serde(rename = "Hotfixes")]
+    pub hotfixes: Vec<String>,
+}
+impl WindowsInfo {
+    /// Create this structure from a SystemInfo_ file path.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref();
+        let system_info = SystemInfo::from_path(path)?;
+
+        Self::from_system_info(&system_info)
+            .map_err(|e| format!("{} for '{}'", e, path.to_string_lossy()))
+    }
+
+    /// Create this structure from a parsed SystemInfo. `OS Name` and `OS Version`
+    /// are required, all other values are optional.
+    pub fn from_system_info(system_info: &SystemInfo) -> Result<Self, String> {
+        let get_string = |key: &str| {
+            system_info.get_value(key)
+                .map(|v| v.to_owned())
+        };
+
+        let name = get_string("OS Name")
+            .ok_or_else(|| "Unable to parse OS Name".to_string())?;
+        let version = get_string("OS Version")
+            .ok_or_else(|| "Unable to parse OS Version".to_string())?;
+
+        let system_locale = system_info.get_value("System Locale")
+            .and_then(|v| v.split(';').next())
+            .map(|v| v.trim().to_owned());
+
+        let hotfixes = system_info.get("Hotfix(s)")
+            .map(|e| e.list_items())
+            .unwrap_or_default();
+
+        let build = parse_build_number(&version);
 
         Ok(
             WindowsInfo {
                 name,
-                version
+                version,
+                build,
+                build_type: get_string("OS Build Type"),
+                system_type: get_string("System Type"),
+                original_install_date: get_string("Original Install Date"),
+                system_locale,
+                time_zone: get_string("Time Zone"),
+                hotfixes
             }
         )
     }
@@ -280,14 +933,16 @@ impl WindowsInfo {
 /// in a recursive folder that contains file lists. This makes it convenient for
 /// iterating the VanillaWindowsReference repo.
 pub struct WinFileListIterator{
+    root: PathBuf,
     dir: walkdir::IntoIter
 }
 impl WinFileListIterator{
     pub fn from_path(path: impl AsRef<Path>) -> Self {
-        let dir = WalkDir::new(&path.as_ref())
+        let root = path.as_ref().to_path_buf();
+        let dir = WalkDir::new(&root)
             .into_iter();
 
-        Self { dir }
+        Self { root, dir }
     }
 }
 impl Iterator for WinFileListIterator {
@@ -315,7 +970,7 @@ impl Iterator for WinFileListIterator {
                     .parent()
                     .expect("Could not get entries' parent.");
 
-                match WindowsFileList::from_folder(&parent) {
+                match WindowsFileList::from_data_set(&self.root, parent) {
                     Ok(fl) => {
                         return Some((parent.to_path_buf(), fl));
                     },
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use serde::Serialize;
+use serde_json::Value;
+use crate::error::VanillaError;
+use crate::index::{get_hash_field, LookupFilter, WindowsRefIndexReader};
+
+/// The max number of documents looked at for a file name
+pub const NAME_HIT_LIMIT: usize = 10_000;
+
+
+/// The classification of a file path (and optional hash) against the index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Verdict {
+    /// The name, directory and hash (when given) have been seen together
+    Vanilla,
+    /// The name is known but has never been seen in this directory
+    /// (e.g. svchost.exe outside of System32)
+    MasqueradeCandidate,
+    /// The full path is known but has never been seen with this hash
+    HashMismatch,
+    /// The name has never been seen
+    Unknown,
+}
+
+
+/// The verdict of a path along with the facts it was derived from.
+#[derive(Debug, Clone, Serialize)]
+pub struct PathVerdict {
+    #[serde(rename = "Verdict")]
+    pub verdict: Verdict,
+    #[serde(rename = "KnownName")]
+    pub known_name: bool,
+    #[serde(rename = "KnownPath")]
+    pub known_path: bool,
+    /// Only set when a hash was given
+    #[serde(rename = "KnownHash")]
+    pub known_hash: Option<bool>,
+    /// The legitimate directories for the name
+    #[serde(rename = "KnownDirectories")]
+    pub known_directories: Vec<String>,
+    /// The name has more documents than were looked at, so KnownDirectories
+    /// can be missing some
+    #[serde(rename = "KnownDirectoriesTruncated")]
+    pub known_directories_truncated: bool,
+}
+
+
+/// Get the first string value of a field from a named document
+fn get_first_str<'a>(hit: &'a Value, field: &str) -> Option<&'a str> {
+    hit.get(field)
+        .and_then(|v| v.get(0))
+        .and_then(|v| v.as_str())
+}
+
+
+/// The directories a file name is known in.
+#[derive(Debug, Clone, Default)]
+pub struct NameDirectories {
+    /// The number of documents with the name
+    pub count: usize,
+    /// lowercase directory -> directory as it was indexed, from the first
+    /// `NAME_HIT_LIMIT` documents
+    pub directories: BTreeMap<String, String>,
+}
+impl NameDirectories {
+    /// The name has been seen
+    pub fn is_known(&self) -> bool {
+        self.count > 0
+    }
+
+    /// Not every document of the name was looked at, so `directories` can be
+    /// missing some
+    pub fn is_truncated(&self) -> bool {
+        self.count > NAME_HIT_LIMIT
+    }
+}
+
+
+/// Get the directories a file name is known in. Only the builds matching
+/// `filter` are used.
+pub fn get_name_directories(
+    index_reader: &WindowsRefIndexReader,
+    name: &str,
+    filter: &LookupFilter
+) -> Result<NameDirectories, VanillaError> {
+    let count = index_reader.count_by_name(name, filter)?;
+    if count == 0 {
+        return Ok(NameDirectories::default());
+    }
+
+    let hits = index_reader.lookup_by_name(name, filter, NAME_HIT_LIMIT)?;
+    let mut directories: BTreeMap<String, String> = BTreeMap::new();
+    for hit in &hits {
+        let hit_directory = get_first_str(hit, "DirectoryName")
+            .unwrap_or_default();
+
+        directories.entry(hit_directory.to_lowercase())
+            .or_insert_with(|| hit_directory.to_owned());
+    }
+
+    Ok( NameDirectories { count, directories } )
+}
+
+
+/// Classify a file by its directory (without the drive letter, e.g. `windows\system32`),
+/// name and optional MD5/SHA256 hash. Only the builds matching `filter` are used.
+pub fn get_path_verdict(
+    index_reader: &WindowsRefIndexReader,
+    directory: &str,
+    name: &str,
+    hash: Option<&str>,
+    filter: &LookupFilter
+) -> Result<PathVerdict, VanillaError> {
+    if let Some(hash) = hash {
+        get_hash_field(hash).map_err(VanillaError::invalid_input)?;
+    }
+    let name_directories = get_name_directories(index_reader, name, filter)?;
+    get_name_path_verdict(index_reader, &name_directories, directory, name, hash, filter)
+}
+
+
+/// Classify a file (see `get_path_verdict`) with the directories of its name
+/// already looked up, so that several paths of a name share one name lookup.
+pub fn get_name_path_verdict(
+    index_reader: &WindowsRefIndexReader,
+    name_directories: &NameDirectories,
+    directory: &str,
+    name: &str,
+    hash: Option<&str>,
+    filter: &LookupFilter
+) -> Result<PathVerdict, VanillaError> {
+    let directory = directory.replace('/', r"\").to_lowercase();
+    if let Some(hash) = hash {
+        get_hash_field(hash).map_err(VanillaError::invalid_input)?;
+    }
+
+    let known_name = name_directories.is_known();
+    // The directories can be truncated for common names, in which case a path
+    // that is not in them is checked with a query
+    let known_path = name_directories.directories.contains_key(&directory) || (
+        name_directories.is_truncated()
+            && index_reader.count_by_name_in_directory(name, &directory, filter)? > 0
+    );
+
+    // A hash can only be known if the path is known
+    let known_hash = match hash {
+        Some(hash) if known_path => {
+            let full_name = if directory.is_empty() {
+                name.to_owned()
+            } else {
+                format!(r"{}\{}", directory, name)
+            };
+            let check = index_reader.get_full_name_hash_check(&full_name, hash, filter)?;
+            Some(check.known_hash)
+        },
+        Some(_) => Some(false),
+        None => None
+    };
+
+    let verdict = if !known_name {
+        Verdict::Unknown
+    } else if !known_path {
+        Verdict::MasqueradeCandidate
+    } else if known_hash == Some(false) {
+        Verdict::HashMismatch
+    } else {
+        Verdict::Vanilla
+    };
+
+    Ok( PathVerdict {
+        verdict,
+        known_name,
+        known_path,
+        known_hash,
+        known_directories: name_directories.directories.values().cloned().collect(),
+        known_directories_truncated: name_directories.is_truncated()
+    })
+}
@@ -0,0 +1,346 @@
+use std::fmt;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Serialize, Serializer};
+
+/// The user name segment of templated `Users\<name>` paths
+pub const USER_TEMPLATE: &str = "%USERNAME%";
+
+/// Profiles in `Users` that are not user accounts and are not templated
+const SHARED_PROFILES: &[&str] = &["Public", "Default", "Default User", "All Users"];
+
+/// Directories that WOW64 and ARM64 emulation redirect between (the native
+/// directory first) and their subdirectories that are not redirected
+const REDIRECTED_DIRECTORIES: &[(&[&str], &[&str])] = &[
+    (
+        &[r"Windows\System32", r"Windows\SysWOW64", r"Windows\SysArm32", r"Windows\SyChpe32"],
+        &["catroot", "catroot2", r"drivers\etc", "LogFiles", "spool"]
+    ),
+    (
+        &[r"Program Files", r"Program Files (x86)", r"Program Files (Arm)"],
+        &[]
+    ),
+];
+
+/// Virtual directories of 32-bit processes and the directory they map to
+const VIRTUAL_DIRECTORIES: &[(&str, &str)] = &[
+    (r"Windows\Sysnative", r"Windows\System32"),
+];
+
+/// Common environment variables (lowercase) and their default values. The user
+/// profile variables expand to the templated profile.
+const ENVIRONMENT_VARIABLES: &[(&str, &str)] = &[
+    ("systemdrive", "C:"),
+    ("systemroot", r"C:\Windows"),
+    ("windir", r"C:\Windows"),
+    ("programfiles", r"C:\Program Files"),
+    ("programfiles(x86)", r"C:\Program Files (x86)"),
+    ("programw6432", r"C:\Program Files"),
+    ("commonprogramfiles", r"C:\Program Files\Common Files"),
+    ("commonprogramfiles(x86)", r"C:\Program Files (x86)\Common Files"),
+    ("commonprogramw6432", r"C:\Program Files\Common Files"),
+    ("programdata", r"C:\ProgramData"),
+    ("allusersprofile", r"C:\ProgramData"),
+    ("public", r"C:\Users\Public"),
+    ("homedrive", "C:"),
+    ("homepath", r"\Users\%USERNAME%"),
+    ("userprofile", r"C:\Users\%USERNAME%"),
+    ("appdata", r"C:\Users\%USERNAME%\AppData\Roaming"),
+    ("localappdata", r"C:\Users\%USERNAME%\AppData\Local"),
+    ("temp", r"C:\Users\%USERNAME%\AppData\Local\Temp"),
+    ("tmp", r"C:\Users\%USERNAME%\AppData\Local\Temp"),
+];
+
+lazy_static! {
+    /// `%NAME%`
+    static ref RE_ENVIRONMENT_VARIABLE: Regex = Regex::new(r"%([^%\\/]+)%").unwrap();
+    /// The NT `\SystemRoot` prefix
+    static ref RE_NT_SYSTEM_ROOT: Regex = Regex::new(r"(?i)^[\\/]systemroot([\\/]|$)").unwrap();
+    /// `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopyN` (GLOBALROOT is optional)
+    static ref RE_SHADOW_COPY: Regex = Regex::new(
+        r"(?i)^(?:\\\\[?.]\\globalroot)?\\device\\harddiskvolumeshadowcopy(\d+)(?:\\|$)"
+    ).unwrap();
+    /// `\Device\HarddiskVolumeN` (or with the `\\?\GLOBALROOT` prefix)
+    static ref RE_DEVICE_VOLUME: Regex = Regex::new(
+        r"(?i)^(?:\\\\[?.]\\globalroot)?\\device\\harddiskvolume(\d+)(?:\\|$)"
+    ).unwrap();
+    /// The `\\?\`, `\\.\` and `\??\` prefixes
+    static ref RE_DEVICE_PREFIX: Regex = Regex::new(r"^(?:\\\\[?.]\\|\\\?\?\\)").unwrap();
+    /// `\\host\C$` or `\\?\UNC\host\C$`
+    static ref RE_ADMIN_SHARE: Regex = Regex::new(
+        r"(?i)^(?:\\\\|unc\\)([^\\]+)\\([a-z])\$(?:\\|$)"
+    ).unwrap();
+    /// `C:`
+    static ref RE_DRIVE: Regex = Regex::new(r"(?i)^([a-z]):(?:\\|$)").unwrap();
+}
+
+
+/// Expand the common environment variables of a path (case insensitive, e.g.
+/// `%SystemRoot%\system32` -> `C:\Windows\system32`) and the NT `\SystemRoot`
+/// prefix. Unknown variables are left as they are.
+pub fn expand_environment_variables(path: &str) -> String {
+    let path = RE_NT_SYSTEM_ROOT.replace(path, r"C:\Windows$1");
+    RE_ENVIRONMENT_VARIABLE.replace_all(&path, |captures: &regex::Captures| {
+        let name = captures[1].to_lowercase();
+        ENVIRONMENT_VARIABLES.iter()
+            .find(|(variable, _)| *variable == name)
+            .map_or_else(|| captures[0].to_string(), |(_, value)| value.to_string())
+    }).to_string()
+}
+
+
+/// Get the prefix directory of a volume relative path on a component
+/// boundary (case insensitive) and the rest of the path, e.g. `Windows\System32`
+/// of `windows\system32\drivers` -> `\drivers`.
+fn strip_directory<'a>(path: &'a str, directory: &str) -> Option<&'a str> {
+    let prefix = path.get(..directory.len())?;
+    let rest = &path[directory.len()..];
+    if prefix.eq_ignore_ascii_case(directory) && (rest.is_empty() || rest.starts_with('\\')) {
+        Some(rest)
+    } else {
+        None
+    }
+}
+
+
+/// Get the directories a volume relative directory is redirected between for
+/// WOW64 and ARM64 processes (`System32`/`SysWOW64`/`SysArm32`/`SyChpe32` and
+/// `Program Files`/`Program Files (x86)`/`Program Files (Arm)`). The directory
+/// itself is first, with `Sysnative` mapped to `System32`, e.g.
+/// `Windows\SysWOW64\drivers` -> `Windows\SysWOW64\drivers`,
+/// `Windows\System32\drivers`, `Windows\SysArm32\drivers`, ... The
+/// subdirectories WOW64 does not redirect (such as `System32\drivers\etc` and
+/// `System32\spool`) are only themselves.
+pub fn get_redirected_directories(directory: &str) -> Vec<String> {
+    let directory = VIRTUAL_DIRECTORIES.iter()
+        .find_map(|(virtual_directory, physical)| strip_directory(directory, virtual_directory)
+            .map(|rest| format!("{}{}", physical, rest)))
+        .unwrap_or_else(|| directory.to_string());
+
+    for (group, exempt) in REDIRECTED_DIRECTORIES {
+        let found = group.iter()
+            .find_map(|member| strip_directory(&directory, member).map(|rest| (*member, rest)));
+
+        if let Some((member, rest)) = found {
+            let subdirectory = rest.trim_start_matches('\\');
+            if exempt.iter().any(|e| strip_directory(subdirectory, e).is_some()) {
+                return vec![directory];
+            }
+
+            let mut directories = vec![directory.clone()];
+            directories.extend(group.iter()
+                .filter(|other| **other != member)
+                .map(|other| format!("{}{}", other, rest)));
+            return directories;
+        }
+    }
+
+    vec![directory]
+}
+
+
+/// The volume a Windows path is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Volume {
+    /// No volume was given (e.g. `\Windows\System32` or `Windows\System32`)
+    None,
+    /// `C:\`, `\\?\C:\`
+    Drive(char),
+    /// `\Device\HarddiskVolumeN\`
+    HarddiskVolume(u32),
+    /// `\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopyN\`
+    ShadowCopy(u32),
+    /// `\\host\C$\`
+    AdminShare {
+        host: String,
+        drive: char
+    },
+}
+impl fmt::Display for Volume {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::None => Ok(()),
+            Self::Drive(drive) => write!(f, r"{}:", drive),
+            Self::HarddiskVolume(n) => write!(f, r"\Device\HarddiskVolume{}", n),
+            Self::ShadowCopy(n) => write!(f, r"\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy{}", n),
+            Self::AdminShare { host, drive } => write!(f, r"\\{}\{}$", host, drive),
+        }
+    }
+}
+
+
+/// A Windows path split into its volume and the path relative to the volume.
+/// Parsing does not depend on the host OS: `/` and `\` are both separators,
+/// repeated separators and `.` are removed and `..` is resolved. Environment
+/// variables are expanded and the account name of user profiles is replaced
+/// with `%USERNAME%` so per-user files match regardless of the account.
+///
+/// The volume relative path is how paths are indexed and looked up (e.g.
+/// `\\?\C:\Windows\System32\cmd.exe` -> `Windows\System32\cmd.exe` and
+/// `C:\Users\jdoe\NTUSER.DAT` -> `Users\%USERNAME%\NTUSER.DAT`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WindowsPath {
+    volume: Volume,
+    /// The volume relative path without leading or trailing separators
+    relative_path: String,
+    /// The account name that was replaced with the template
+    user: Option<String>,
+}
+impl WindowsPath {
+    /// Parse the path of a file. Anything that is not a known volume prefix is
+    /// part of the volume relative path.
+    pub fn new(path: &str) -> Self {
+        Self::parse(path, false)
+    }
+
+    /// Parse the path of a directory. Unlike a file path, the last component
+    /// can be a user profile (e.g. `C:\Users\jdoe` -> `Users\%USERNAME%`).
+    pub fn from_directory(path: &str) -> Self {
+        Self::parse(path, true)
+    }
+
+    fn parse(path: &str, is_directory: bool) -> Self {
+        let path = expand_environment_variables(Self::strip_input(path))
+            .replace('/', r"\");
+        let (volume, rest) = Self::split_volume(&path);
+
+        let mut components: Vec<&str> = Vec::new();
+        for component in rest.split('\\') {
+            match component {
+                "" | "." => {},
+                ".." => { components.pop(); },
+                c => components.push(c)
+            }
+        }
+
+        let user = Self::template_user(&mut components, is_directory);
+
+        Self {
+            volume,
+            relative_path: components.join(r"\"),
+            user
+        }
+    }
+
+    /// Remove the line ending and surrounding quotes a path was read with. Other
+    /// whitespace is kept, a trailing space is part of the name (e.g. the
+    /// `explorer.exe ` masquerade).
+    fn strip_input(path: &str) -> &str {
+        let path = path.trim_end_matches(['\r', '\n']);
+        path.strip_prefix('"')
+            .and_then(|p| p.strip_suffix('"'))
+            .unwrap_or(path)
+    }
+
+    /// Replace the account name of a `Users\<name>` path with the template and
+    /// return it. Names with glob characters (which can't be in a file name) are
+    /// left as they are so that searches still match every profile.
+    fn template_user(components: &mut [&str], is_directory: bool) -> Option<String> {
+        let min_components = if is_directory { 2 } else { 3 };
+        if components.len() < min_components || !components[0].eq_ignore_ascii_case("Users") {
+            return None;
+        }
+
+        let name = components[1];
+        if name == USER_TEMPLATE
+            || name.contains(['*', '?'])
+            || SHARED_PROFILES.iter().any(|p| p.eq_ignore_ascii_case(name)) {
+            return None;
+        }
+
+        components[1] = USER_TEMPLATE;
+        Some(name.to_string())
+    }
+
+    /// Split the volume from the rest of a path that only uses `\`
+    fn split_volume(path: &str) -> (Volume, &str) {
+        let get_number = |c: &regex::Captures| c.get(1)
+            .and_then(|n| n.as_str().parse::<u32>().ok())
+            .unwrap_or_default();
+        let get_drive = |s: &str| s.chars()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+
+        if let Some(captures) = RE_SHADOW_COPY.captures(path) {
+            return (Volume::ShadowCopy(get_number(&captures)), &path[captures[0].len()..]);
+        }
+        if let Some(captures) = RE_DEVICE_VOLUME.captures(path) {
+            return (Volume::HarddiskVolume(get_number(&captures)), &path[captures[0].len()..]);
+        }
+
+        // `\\?\C:\` and `\\?\UNC\host\C$\` are handled as `C:\` and `\\host\C$\`
+        let (path, device_prefix) = match RE_DEVICE_PREFIX.find(path) {
+            Some(m) => (&path[m.end()..], true),
+            None => (path, false)
+        };
+
+        if let Some(captures) = RE_ADMIN_SHARE.captures(path) {
+            let is_unc = captures[0].starts_with(r"\\") != device_prefix;
+            if is_unc {
+                return (
+                    Volume::AdminShare {
+                        host: captures[1].to_string(),
+                        drive: get_drive(&captures[2])
+                    },
+                    &path[captures[0].len()..]
+                );
+            }
+        }
+        if let Some(captures) = RE_DRIVE.captures(path) {
+            return (Volume::Drive(get_drive(&captures[1])), &path[captures[0].len()..]);
+        }
+
+        (Volume::None, path)
+    }
+
+    /// The volume of the path
+    pub fn volume(&self) -> &Volume {
+        &self.volume
+    }
+
+    /// The path relative to the volume (e.g. `Windows\System32\cmd.exe`)
+    pub fn relative_path(&self) -> &str {
+        &self.relative_path
+    }
+
+    /// The account name of a user profile path (see `USER_TEMPLATE`)
+    pub fn user(&self) -> Option<&str> {
+        self.user.as_deref()
+    }
+
+    /// The last component of the path (e.g. `cmd.exe`). Empty for the root of
+    /// a volume.
+    pub fn file_name(&self) -> &str {
+        self.relative_path.rsplit('\\')
+            .next()
+            .unwrap_or_default()
+    }
+
+    /// The volume relative directory of the path (e.g. `Windows\System32`).
+    /// Empty for files in the root of a volume.
+    pub fn parent(&self) -> &str {
+        self.relative_path.rsplit_once('\\')
+            .map(|(parent, _)| parent)
+            .unwrap_or_default()
+    }
+}
+/// Displays the path as it was on the host, with the account name of a
+/// templated user profile (e.g. `C:\Users\jdoe\NTUSER.DAT`).
+impl fmt::Display for WindowsPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let relative_path = match &self.user {
+            Some(user) => self.relative_path.replacen(USER_TEMPLATE, user, 1),
+            None => self.relative_path.clone()
+        };
+        match self.volume {
+            Volume::None => write!(f, "{}", relative_path),
+            _ => write!(f, r"{}\{}", self.volume, relative_path)
+        }
+    }
+}
+impl Serialize for WindowsPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
@@ -0,0 +1,35 @@
+// Not every test crate uses all of the helpers
+#![allow(dead_code)]
+
+use std::path::Path;
+use tantivy::Index;
+use winvanilla::index::{generate_schema_from_vanilla, WindowsRefIndexReader, WindowRefIndexWriter};
+
+
+pub const SAMPLES: &str = "samples";
+
+
+/// Index the samples folder into a RAM index
+pub fn get_sample_reader() -> WindowsRefIndexReader {
+    let schema = generate_schema_from_vanilla(SAMPLES)
+        .expect("Error generating schema from samples.");
+    let index = Index::create_in_ram(schema);
+
+    let mut writer = WindowRefIndexWriter::from_index(SAMPLES, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    writer.index().expect("Error indexing samples.");
+
+    WindowsRefIndexReader::try_from(index)
+        .expect("Error creating WindowsRefIndexReader.")
+}
+
+
+/// Copy the sample data set into a folder
+pub fn copy_sample_data_set(destination: &Path) {
+    std::fs::create_dir_all(destination).expect("Error creating data set folder.");
+    let source = Path::new(SAMPLES).join("W10_20H2_Pro_19042.631");
+    for name in ["SystemInfo_W10_Pro_20H2_19042.txt", "W10_Pro_20H2_19042.csv"] {
+        std::fs::copy(source.join(name), destination.join(name))
+            .expect("Error copying sample file.");
+    }
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use std::path::Path;
+use tantivy::Index;
+use tempfile::TempDir;
+use common::copy_sample_data_set;
+use winvanilla::diff::{diff_file_lists, diff_index_versions, BuildDiff, Change};
+use winvanilla::index::{generate_schema_from_vanilla, WindowsRefIndexReader, WindowRefIndexWriter};
+use winvanilla::triage::ReportFormat;
+use winvanilla::vanilla::WindowsFileList;
+
+const CSV_NAME: &str = "W10_Pro_20H2_19042.csv";
+const SYSTEM_INFO_NAME: &str = "SystemInfo_W10_Pro_20H2_19042.txt";
+
+
+/// Create the old (sample) and a new data set in a reference root. The new data
+/// set is build 19043, has no notepad.exe, a different ntfs.sys and a new file.
+fn create_builds(root: &Path) -> (WindowsFileList, WindowsFileList) {
+    let old = root.join("W10_20H2_Pro_19042.631");
+    let new = root.join("W10_21H1_Pro_19043.1052");
+    copy_sample_data_set(&old);
+    copy_sample_data_set(&new);
+
+    let csv_path = new.join(CSV_NAME);
+    let content = std::fs::read_to_string(&csv_path).expect("Error reading csv.");
+    let mut lines: Vec<String> = content.lines()
+        .filter(|l| !l.contains("notepad.exe"))
+        .map(|l| l.replace(r#""2902856""#, r#""2902857""#))
+        .collect();
+    lines.push(lines[2].replace("test.csv", "new.csv"));
+    std::fs::write(&csv_path, lines.join("\r\n")).expect("Error writing csv.");
+
+    let info_path = new.join(SYSTEM_INFO_NAME);
+    let content = std::fs::read_to_string(&info_path).expect("Error reading SystemInfo.")
+        .replace("10.0.19042 N/A Build 19042", "10.0.19043 N/A Build 19043");
+    std::fs::write(&info_path, content).expect("Error writing SystemInfo.");
+
+    (
+        WindowsFileList::from_folder(&old).expect("Error reading old file list."),
+        WindowsFileList::from_folder(&new).expect("Error reading new file list.")
+    )
+}
+
+
+/// Check the diff of the sample builds
+fn check_diff(diff: &BuildDiff) {
+    assert_eq!((diff.added, diff.removed, diff.modified), (1, 1, 1));
+    assert_eq!(diff.changes.len(), 3);
+
+    assert_eq!(diff.changes[0].change, Change::Added);
+    assert_eq!(diff.changes[0].full_name, "new.csv");
+    assert_eq!(diff.changes[1].change, Change::Modified);
+    assert_eq!(diff.changes[1].full_name.to_lowercase(), r"windows\system32\drivers\ntfs.sys");
+    assert_eq!(diff.changes[1].old.as_ref().and_then(|s| s.length), Some(2902856));
+    assert_eq!(diff.changes[1].new.as_ref().and_then(|s| s.length), Some(2902857));
+    assert_eq!(diff.changes[2].change, Change::Removed);
+    assert_eq!(diff.changes[2].full_name.to_lowercase(), r"windows\system32\notepad.exe");
+}
+
+
+#[test]
+fn test_diff_file_lists() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let (old, new) = create_builds(temp_dir.path());
+
+    let diff = diff_file_lists(&old, &new).expect("Error diffing file lists.");
+    check_diff(&diff);
+    assert_eq!(diff.unchanged, 14);
+
+    let mut output = Vec::new();
+    diff.write_changes(&mut output, ReportFormat::Csv).expect("Error writing changes.");
+    let output = String::from_utf8(output).expect("Output is not utf8.");
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "Change,FullName,OldLength,NewLength,OldMD5,NewMD5,OldSHA256,NewSHA256");
+    assert!(lines[2].starts_with(r"Modified,Windows\System32\drivers\ntfs.sys,2902856,2902857,"));
+
+    let mut output = Vec::new();
+    diff.write_changes(&mut output, ReportFormat::Jsonl).expect("Error writing changes.");
+    assert_eq!(String::from_utf8(output).unwrap().lines().count(), 3);
+
+    // No changes
+    let diff = diff_file_lists(&old, &old).expect("Error diffing file lists.");
+    assert!(diff.changes.is_empty());
+}
+
+
+#[test]
+fn test_diff_index_versions() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path();
+    create_builds(root);
+
+    let schema = generate_schema_from_vanilla(root)
+        .expect("Error generating schema.");
+    let index = Index::create_in_ram(schema);
+    let mut writer = WindowRefIndexWriter::from_index(root, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    writer.index().expect("Error indexing.");
+    let reader = WindowsRefIndexReader::try_from(index)
+        .expect("Error creating reader.");
+
+    let diff = diff_index_versions(&reader, "10.0.19042 N/A Build 19042", "10.0.19043 N/A Build 19043")
+        .expect("Error diffing versions.");
+    check_diff(&diff);
+
+    assert!(diff_index_versions(&reader, "10.0.19042 N/A Build 19042", "10.0.18363 N/A Build 18363").is_err());
+}
+
+
+#[test]
+fn test_diff_index_data_sets() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path();
+    create_builds(root);
+    // Another edition of the old OS version
+    copy_sample_data_set(&root.join("W10_20H2_Ent_19042.631"));
+
+    let schema = generate_schema_from_vanilla(root)
+        .expect("Error generating schema.");
+    let index = Index::create_in_ram(schema);
+    let mut writer = WindowRefIndexWriter::from_index(root, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    writer.index().expect("Error indexing.");
+    let reader = WindowsRefIndexReader::try_from(index)
+        .expect("Error creating reader.");
+
+    let error = diff_index_versions(&reader, "10.0.19042 N/A Build 19042", "10.0.19043 N/A Build 19043")
+        .expect_err("An OS version in two data sets should error.");
+    assert!(error.to_string().contains("W10_20H2_Ent_19042.631, W10_20H2_Pro_19042.631"), "{}", error);
+
+    let diff = diff_index_versions(&reader, "W10_20H2_Pro_19042.631", "W10_21H1_Pro_19043.1052")
+        .expect("Error diffing data sets.");
+    check_diff(&diff);
+    let diff = diff_index_versions(&reader, "W10_20H2_Pro_19042.631", "10.0.19043 N/A Build 19043")
+        .expect("Error diffing data sets.");
+    check_diff(&diff);
+}
@@ -0,0 +1,171 @@
+mod common;
+
+use std::path::Path;
+use rusqlite::Connection;
+use tempfile::TempDir;
+use common::copy_sample_data_set;
+use winvanilla::export::{matches_os_version, ExportStats};
+use winvanilla::export::hashlist::{export_hash_list, get_checksum_line, HashListFormat};
+use winvanilla::export::nsrl::{export_rds, LegacyRdsWriter, Rds3Writer};
+
+
+/// Copy the sample data set into two data set folders of a reference root
+fn create_reference(root: &Path) {
+    copy_sample_data_set(&root.join("W10_20H2_Pro_19042.631"));
+    copy_sample_data_set(&root.join("W10_20H2_Pro_19042.631_copy"));
+}
+
+
+const EXPECTED_STATS: ExportStats = ExportStats {
+    data_sets: 2,
+    files: 13,
+    duplicates: 13,
+    skipped: 6
+};
+
+
+#[test]
+fn test_nsrl_legacy_export() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path().join("reference");
+    create_reference(&root);
+
+    let output = temp_dir.path().join("rds");
+    let mut writer = LegacyRdsWriter::create(&output).expect("Error creating writer.");
+    let stats = export_rds(&root, &[], &mut writer).expect("Error exporting.");
+    assert_eq!(stats, EXPECTED_STATS);
+
+    let read_lines = |name: &str| std::fs::read_to_string(output.join(name))
+        .expect("Error reading export.")
+        .lines()
+        .map(|l| l.to_string())
+        .collect::<Vec<String>>();
+
+    let files = read_lines("NSRLFile.txt");
+    assert_eq!(files.len(), 14);
+    assert_eq!(files[0], r#""SHA-1","MD5","CRC32","FileName","FileSize","ProductCode","OpSystemCode","SpecialCode""#);
+    assert_eq!(files[1], r#""","C590A84B8C72CF18F35AE166F815C9DF","","PsExec_IgnoreThisFile_ResearchTool.exe",834936,1,1,"""#);
+
+    let products = read_lines("NSRLProd.txt");
+    assert_eq!(products.len(), 2);
+    assert_eq!(products[1], r#"1,"Microsoft Windows 10 Pro","10.0.19042 N/A Build 19042",1,1,"en-us","Operating System""#);
+
+    assert_eq!(read_lines("NSRLOS.txt").len(), 2);
+    assert_eq!(read_lines("NSRLMfg.txt")[1], r#"1,"Microsoft Corporation""#);
+}
+
+
+#[test]
+fn test_nsrl_rds3_export() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path().join("reference");
+    create_reference(&root);
+
+    let output = temp_dir.path().join("rds.db");
+
+    // An unfinished export leaves no file behind, so it can be run again
+    let writer = Rds3Writer::create(&output).expect("Error creating writer.");
+    assert!(temp_dir.path().join("rds.db.tmp").exists());
+    drop(writer);
+    assert!(!temp_dir.path().join("rds.db.tmp").exists());
+    assert!(!output.exists());
+
+    let mut writer = Rds3Writer::create(&output).expect("Error creating writer.");
+    let stats = export_rds(&root, &[], &mut writer).expect("Error exporting.");
+    assert_eq!(stats, EXPECTED_STATS);
+    drop(writer);
+    assert!(!temp_dir.path().join("rds.db.tmp").exists());
+
+    let connection = Connection::open(&output).expect("Error opening export.");
+    let count = |sql: &str| -> i64 {
+        connection.query_row(sql, [], |row| row.get(0))
+            .expect("Error querying export.")
+    };
+    assert_eq!(count("SELECT COUNT(*) FROM FILE"), 13);
+    assert_eq!(count("SELECT COUNT(*) FROM PKG"), 1);
+    assert_eq!(count("SELECT COUNT(*) FROM OS"), 1);
+    assert_eq!(count("SELECT COUNT(*) FROM MFG"), 1);
+    assert_eq!(
+        count("SELECT file_size FROM FILE JOIN PKG USING (package_id) \
+            WHERE md5 = '17EB8E0DD3D4211CDE089DFD9A238891' AND PKG.name = 'Microsoft Windows 10 Pro'"),
+        289792
+    );
+
+    // An existing database is not exported into again
+    assert!(Rds3Writer::create(&output).is_err());
+}
+
+
+#[test]
+fn test_hash_list_exports() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path().join("reference");
+    create_reference(&root);
+
+    // The same cmd.exe in another folder is a duplicate hash
+    let csv_path = root.join("W10_20H2_Pro_19042.631_copy").join("W10_Pro_20H2_19042.csv");
+    let csv = std::fs::read_to_string(&csv_path).expect("Error reading sample csv.");
+    let cmd_line = csv.lines()
+        .find(|l| l.contains(r"System32\cmd.exe"))
+        .expect("Sample has no cmd.exe.")
+        .replace("System32", "Temp");
+    std::fs::write(&csv_path, format!("{}{}\r\n", csv, cmd_line))
+        .expect("Error writing sample csv.");
+
+    let export = |format: HashListFormat, os_versions: &[String]| -> (ExportStats, Vec<String>) {
+        let mut output = Vec::new();
+        let stats = export_hash_list(&root, os_versions, format, &mut output)
+            .expect("Error exporting.");
+        let lines = String::from_utf8(output)
+            .expect("Export is not utf8.")
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+        (stats, lines)
+    };
+
+    let (stats, lines) = export(HashListFormat::Hashdeep, &[]);
+    assert_eq!(stats, ExportStats { duplicates: 14, ..EXPECTED_STATS });
+    assert_eq!(lines.len(), 4 + 13);
+    assert_eq!(lines[0], "%%%% HASHDEEP-1.0");
+    assert_eq!(lines[1], "%%%% size,md5,sha256,filename");
+    assert_eq!(
+        lines[4],
+        r"834936,c590a84b8c72cf18f35ae166f815c9df,57492d33b7c0755bb411b22d2dfdfdf088cbbfcd010e30dd8d425d5fe66adff4,C:\PsExec_IgnoreThisFile_ResearchTool.exe"
+    );
+
+    let (stats, lines) = export(HashListFormat::Md5sum, &["10.0.19042".to_string()]);
+    assert_eq!(stats.files, 13);
+    assert_eq!(lines[0], r"\c590a84b8c72cf18f35ae166f815c9df  C:\\PsExec_IgnoreThisFile_ResearchTool.exe");
+
+    let (stats, lines) = export(HashListFormat::Sha256sum, &[]);
+    assert_eq!(stats.files, 13);
+    assert!(!lines.iter().any(|l| l.contains(r"Temp\\cmd.exe")));
+    assert!(lines.iter().all(|l| l.len() > 64 + 2));
+
+    let (stats, lines) = export(HashListFormat::HfindMd5, &[]);
+    assert_eq!(stats.files, 13);
+    assert_eq!(stats.duplicates, 14);
+    let mut sorted = lines.clone();
+    sorted.sort();
+    assert_eq!(lines, sorted);
+    assert!(lines.contains(&r"17eb8e0dd3d4211cde089dfd9a238891  C:\Windows\System32\cmd.exe".to_string()));
+
+    // No data set matches the OS version
+    let (stats, lines) = export(HashListFormat::Md5sum, &["10.0.18363".to_string()]);
+    assert_eq!(stats, ExportStats::default());
+    assert!(lines.is_empty());
+}
+
+
+#[test]
+fn test_export_helpers() {
+    assert_eq!(get_checksum_line("abc", "cmd.exe"), "abc  cmd.exe");
+    assert_eq!(get_checksum_line("abc", r"C:\cmd.exe"), r"\abc  C:\\cmd.exe");
+
+    let version = "10.0.19042 N/A Build 19042";
+    assert!(matches_os_version(version, &[]));
+    assert!(matches_os_version(version, &["10.0.19042 n/a build 19042".to_string()]));
+    assert!(matches_os_version(version, &["10.0.18363".to_string(), "10.0.19042".to_string()]));
+    assert!(!matches_os_version(version, &["10.0.1904".to_string()]));
+}
@@ -0,0 +1,102 @@
+mod common;
+
+use sha2::{Digest, Sha256};
+use tempfile::TempDir;
+use common::copy_sample_data_set;
+use winvanilla::filter::{get_hash_key, HashFilter, HashFilterBuilder};
+
+
+/// A SHA256 hex string of a number
+fn get_sha256(value: u64) -> String {
+    format!("{:x}", Sha256::digest(value.to_le_bytes()))
+}
+
+
+#[test]
+fn test_filter_false_positive_rate() {
+    let mut builder = HashFilterBuilder::new();
+    for i in 0..100_000 {
+        builder.add_hash(&get_sha256(i)).expect("Error adding hash.");
+    }
+    let filter = builder.build().expect("Error building filter.");
+    assert_eq!(filter.header().key_count, 100_000);
+
+    for i in 0..100_000 {
+        assert!(filter.contains(&get_sha256(i)).unwrap());
+    }
+
+    // About 1.5 false positives are expected
+    let false_positives = (100_000..200_000)
+        .filter(|i| filter.contains(&get_sha256(*i)).unwrap())
+        .count();
+    assert!(false_positives <= 20, "{} false positives", false_positives);
+}
+
+
+#[test]
+fn test_filter_from_reference() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path().join("reference");
+    copy_sample_data_set(&root.join("W10_20H2_Pro_19042.631"));
+
+    let filter = HashFilterBuilder::from_vanilla(&root, &[])
+        .expect("Error reading reference.")
+        .build()
+        .expect("Error building filter.");
+    assert_eq!(filter.header().os_versions, vec!["10.0.19042 N/A Build 19042".to_string()]);
+
+    // cmd.exe MD5 in either case
+    assert!(filter.contains("17EB8E0DD3D4211CDE089DFD9A238891").unwrap());
+    assert!(filter.contains("17eb8e0dd3d4211cde089dfd9a238891").unwrap());
+    assert!(filter.contains("57492d33b7c0755bb411b22d2dfdfdf088cbbfcd010e30dd8d425d5fe66adff4").unwrap());
+    assert!(filter.contains("not a hash").is_err());
+
+    // Write and read the filter back
+    let path = temp_dir.path().join("vanilla.filter");
+    filter.to_path(&path).expect("Error writing filter.");
+    let read = HashFilter::from_path(&path).expect("Error reading filter.");
+    assert_eq!(read.header(), filter.header());
+    assert!(read.contains("17EB8E0DD3D4211CDE089DFD9A238891").unwrap());
+
+    // No data set matches the OS version
+    assert!(HashFilterBuilder::from_vanilla(&root, &["10.0.18363".to_string()])
+        .expect("Error reading reference.")
+        .build()
+        .expect("Error building filter.")
+        .header()
+        .os_versions
+        .is_empty());
+
+    assert!(HashFilter::read_from(&b"NOTAFILTER"[..]).is_err());
+
+    // Corrupt sizes are refused before anything is allocated
+    let mut data = b"WVFILTER".to_vec();
+    data.extend(1u32.to_le_bytes());
+    data.extend(u32::MAX.to_le_bytes());
+    assert_eq!(
+        HashFilter::read_from(&data[..]).err(),
+        Some(format!("Invalid filter header size: {}", u32::MAX))
+    );
+
+    let mut data = std::fs::read(&path).expect("Error reading filter file.");
+    let header_size = u32::from_le_bytes(data[12..16].try_into().unwrap()) as usize;
+    // The fingerprint count (u64 LE) follows the seed and the three segment values
+    let count_offset = 16 + header_size + 8 + 4 * 3;
+    data[count_offset..count_offset + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert_eq!(
+        HashFilter::read_from(&data[..]).err(),
+        Some("Error reading filter: LimitExceeded".to_string())
+    );
+}
+
+
+#[test]
+fn test_get_hash_key() {
+    assert_eq!(get_hash_key("17EB8E0DD3D4211CDE089DFD9A238891"), Ok(0x17eb8e0dd3d4211c));
+    assert_eq!(get_hash_key("17eb8e0dd3d4211cde089dfd9a238891"), Ok(0x17eb8e0dd3d4211c));
+    assert!(get_hash_key("17eb8e0dd3d4211c").is_err());
+    // Every character must be a hex digit
+    assert!(get_hash_key("17eb8e0dd3d4211cde089dfd9a23889z").is_err());
+    assert!(get_hash_key("+7eb8e0dd3d4211cde089dfd9a238891").is_err());
+    assert!(get_hash_key(&format!("{}{}", "17eb8e0dd3d4211c", "-".repeat(48))).is_err());
+}
@@ -0,0 +1,408 @@
+mod common;
+
+use tantivy::Index;
+use tempfile::TempDir;
+use serde_json::json;
+use common::{copy_sample_data_set, get_sample_reader};
+use winvanilla::index::{
+    generate_schema_from_vanilla, glob_to_regex, IncrementalIndexStats, LookupFilter,
+    WindowsRefIndexReader, WindowRefIndexWriter, MAX_SEARCH_WINDOW
+};
+use winvanilla::error::ErrorKind;
+use winvanilla::verdict::{get_name_path_verdict, get_path_verdict, NameDirectories, Verdict, NAME_HIT_LIMIT};
+use winvanilla::winpath::WindowsPath;
+
+
+#[test]
+fn test_range_queries() {
+    let reader = get_sample_reader();
+
+    let hits = reader.get_query_hits(
+        r#"DirectoryName:"windows\system32" AND Length:>10000000 AND OsBuild:>=19041 AND CreationTimeUtc:>2021-01-01T00:00:00Z"#,
+        10
+    ).expect("Error running range query.");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["Name"][0], "edgehtml.dll");
+    assert_eq!(hits[0]["Length"][0], 24381440);
+    assert_eq!(hits[0]["OsBuild"][0], 19042);
+
+    let hits = reader.get_query_hits(r#"Name:"edgehtml.dll" AND LastAccessTimeUtc:>2021-01-01T00:00:00Z"#, 10)
+        .expect("Error running range query.");
+    assert!(!hits.is_empty());
+
+    let hits = reader.get_query_hits("OsBuild:<19041", 10)
+        .expect("Error running range query.");
+    assert!(hits.is_empty());
+}
+
+
+#[test]
+fn test_path_verdict() {
+    let reader = get_sample_reader();
+    let filter = LookupFilter::default();
+
+    let verdict = get_path_verdict(&reader, r"Windows\System32", "svchost.exe", None, &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::Vanilla);
+    assert_eq!(verdict.known_directories, vec![r"Windows\System32", r"Windows\SysWOW64"]);
+
+    let verdict = get_path_verdict(&reader, r"users\public", "svchost.exe", None, &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::MasqueradeCandidate);
+
+    let verdict = get_path_verdict(&reader, "", "PsExec_IgnoreThisFile_ResearchTool.exe", Some("C590A84B8C72CF18F35AE166F815C9DF"), &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::Vanilla);
+    assert_eq!(verdict.known_hash, Some(true));
+
+    let verdict = get_path_verdict(&reader, r"windows\system32", "cmd.exe", Some("D760CBE8ADA11F064A08D60AF62FD9F2"), &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::HashMismatch);
+
+    let verdict = get_path_verdict(&reader, r"windows\system32", "svch0st.exe", None, &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::Unknown);
+
+    // A directory that was cut off from the directories of a common name is
+    // still a known path
+    let truncated = NameDirectories { count: NAME_HIT_LIMIT + 1, ..Default::default() };
+    let verdict = get_name_path_verdict(&reader, &truncated, r"Windows\SysWOW64", "svchost.exe", None, &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::Vanilla);
+    assert!(verdict.known_directories_truncated);
+}
+
+
+#[test]
+fn test_full_name_hash_check() {
+    let reader = get_sample_reader();
+    let filter = LookupFilter::default();
+
+    let check = reader.get_full_name_hash_check(
+        r"Program Files\Common Files\microsoft shared\ink\Alphabet.xml",
+        "E066F5907F9EFDB760DA0377A7B5664C815D667FB2A7B370AA4A49783F4FEA0D",
+        &filter
+    ).expect("Error checking hash.");
+    assert!(check.known_path);
+    assert!(check.known_hash);
+
+    let check = reader.get_full_name_hash_check(
+        r"program files\common files\microsoft shared\ink\alphabet.xml",
+        "D760CBE8ADA11F064A08D60AF62FD9F2",
+        &filter
+    ).expect("Error checking hash.");
+    assert!(check.known_path);
+    assert!(!check.known_hash);
+    assert_eq!(check.seen_hashes.len(), 1);
+    assert_eq!(check.seen_hashes[0].md5.as_deref(), Some("6176656C4D6A215BD670D5BD63D35B59"));
+    assert_eq!(check.seen_hashes[0].os_versions, vec!["10.0.19042 N/A Build 19042"]);
+    assert!(!check.seen_hashes_truncated);
+
+    // Files in the root of the volume
+    let check = reader.get_full_name_hash_check(
+        "PsExec_IgnoreThisFile_ResearchTool.exe",
+        "c590a84b8c72cf18f35ae166f815c9df",
+        &filter
+    ).expect("Error checking hash.");
+    assert!(check.known_path);
+    assert!(check.known_hash);
+    assert!(check.seen_hashes.is_empty());
+
+    let check = reader.get_full_name_hash_check(r"windows\system32\svch0st.exe", "D760CBE8ADA11F064A08D60AF62FD9F2", &filter)
+        .expect("Error checking hash.");
+    assert!(!check.known_path);
+    assert!(!check.known_hash);
+}
+
+
+#[test]
+fn test_term_lookups() {
+    let reader = get_sample_reader();
+    let filter = LookupFilter::default();
+
+    // Parentheses are matched as is
+    let hits = reader.lookup_by_directory(r"Program Files (x86)\Internet Explorer", &filter, 10)
+        .expect("Error looking up directory.");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["Name"][0], "iexplore.exe");
+
+    // Query syntax in values is not interpreted
+    let hits = reader.lookup_by_name(r#"cmd.exe" OR Name:"svchost.exe"#, &filter, 10)
+        .expect("Error looking up name.");
+    assert!(hits.is_empty());
+
+    // Files in the root of the volume have an empty DirectoryName
+    let hits = reader.lookup_by_directory("", &filter, 10)
+        .expect("Error looking up directory.");
+    assert_eq!(hits.len(), 3);
+
+    let hits = reader.lookup_by_name_in_directory("CMD.EXE", r"windows\syswow64", &filter, 10)
+        .expect("Error looking up name in directory.");
+    assert_eq!(hits.len(), 1);
+    assert_eq!(hits[0]["FullName"][0], r"Windows\SysWOW64\cmd.exe");
+
+    let hits = reader.lookup_by_hash("6176656c4d6a215bd670d5bd63d35b59", &filter, 10)
+        .expect("Error looking up hash.");
+    assert_eq!(hits.len(), 1);
+}
+
+
+#[test]
+fn test_lookup_filter() {
+    let reader = get_sample_reader();
+
+    let filter = LookupFilter {
+        os_name: Some("microsoft windows 10 pro".to_string()),
+        min_build: Some(19041),
+        max_build: Some(19042),
+        ..Default::default()
+    };
+    let hits = reader.lookup_by_name("cmd.exe", &filter, 10)
+        .expect("Error looking up name.");
+    assert_eq!(hits.len(), 2);
+
+    // Only builds of a Windows 10 1909 host
+    let filter = LookupFilter {
+        min_build: Some(18363),
+        max_build: Some(18363),
+        ..Default::default()
+    };
+    let hits = reader.lookup_by_name("cmd.exe", &filter, 10)
+        .expect("Error looking up name.");
+    assert!(hits.is_empty());
+
+    let verdict = get_path_verdict(&reader, r"windows\system32", "cmd.exe", None, &filter)
+        .expect("Error getting verdict.");
+    assert_eq!(verdict.verdict, Verdict::Unknown);
+
+    let filter = LookupFilter {
+        os_version: Some("10.0.19042 N/A Build 19042".to_string()),
+        ..Default::default()
+    };
+    let hits = reader.lookup_by_hashes(&["6176656c4d6a215bd670d5bd63d35b59".to_string()], &filter, 10)
+        .expect("Error looking up hashes.");
+    assert_eq!(hits[0].len(), 1);
+
+    // Every document of a data set
+    let filter = LookupFilter {
+        data_set_path: Some("W10_20H2_Pro_19042.631".to_string()),
+        ..Default::default()
+    };
+    let mut names = Vec::new();
+    reader.for_each_doc(&filter, |hit| names.push(hit["Name"][0].to_string()))
+        .expect("Error reading documents.");
+    assert_eq!(names.len(), 16);
+
+    let filter = LookupFilter {
+        min_build: Some(19042),
+        max_build: Some(19041),
+        ..Default::default()
+    };
+    let error = reader.lookup_by_name("cmd.exe", &filter, 10)
+        .expect_err("Expected an invalid build range.");
+    assert_eq!(error.kind(), ErrorKind::InvalidInput);
+}
+
+
+#[test]
+fn test_incremental_index() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path();
+    copy_sample_data_set(&root.join("Windows10").join("20H2").join("W10_20H2_Pro_19042.631"));
+
+    let schema = generate_schema_from_vanilla(root)
+        .expect("Error generating schema.");
+    let index = Index::create_in_ram(schema);
+    let mut writer = WindowRefIndexWriter::from_index(root, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    let cmd_count = || {
+        WindowsRefIndexReader::try_from(index.clone())
+            .expect("Error creating reader.")
+            .lookup_by_name("cmd.exe", &LookupFilter::default(), 100)
+            .expect("Error looking up name.")
+            .len()
+    };
+
+    let stats = writer.index_incremental().expect("Error indexing.");
+    assert_eq!(stats, IncrementalIndexStats { added: 1, changed: 0, removed: 0, unchanged: 0 });
+    assert_eq!(cmd_count(), 2);
+
+    let indexed = writer.get_indexed_data_sets().expect("Error getting data sets.");
+    assert!(indexed.contains_key("Windows10/20H2/W10_20H2_Pro_19042.631"));
+
+    // A new data set
+    let second = root.join("Windows10").join("20H2").join("W10_20H2_Ent_19042.631");
+    copy_sample_data_set(&second);
+    let stats = writer.index_incremental().expect("Error indexing.");
+    assert_eq!(stats, IncrementalIndexStats { added: 1, changed: 0, removed: 0, unchanged: 1 });
+    assert_eq!(cmd_count(), 4);
+
+    // The data sets of a hit can be told apart
+    let hits = WindowsRefIndexReader::try_from(index.clone())
+        .expect("Error creating reader.")
+        .get_query_hits(r#"Name:"cmd.exe" AND Edition:"ent""#, 10)
+        .expect("Error running query.");
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[0]["DataSetPath"], json!(["Windows10/20H2/W10_20H2_Ent_19042.631"]));
+    assert_eq!(hits[0]["Edition"][0], "Ent");
+    assert_eq!(hits[0]["Architecture"][0], "x64");
+    assert_eq!(hits[0]["Release"][0], "20H2");
+    assert_eq!(hits[0]["FullBuild"][0], "19042.631");
+
+    // A changed data set (remove the last row)
+    let csv_path = second.join("W10_Pro_20H2_19042.csv");
+    let content = std::fs::read_to_string(&csv_path).expect("Error reading csv.");
+    let content = content.trim_end().rsplit_once("\r\n").expect("No rows.").0.to_string();
+    std::fs::write(&csv_path, content).expect("Error writing csv.");
+    let stats = writer.index_incremental().expect("Error indexing.");
+    assert_eq!(stats, IncrementalIndexStats { added: 0, changed: 1, removed: 0, unchanged: 1 });
+    assert_eq!(cmd_count(), 4);
+
+    // A removed data set
+    std::fs::remove_dir_all(&second).expect("Error removing data set.");
+    let stats = writer.index_incremental().expect("Error indexing.");
+    assert_eq!(stats, IncrementalIndexStats { added: 0, changed: 0, removed: 1, unchanged: 1 });
+    assert_eq!(cmd_count(), 2);
+}
+
+
+#[test]
+fn test_build_history() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path();
+    copy_sample_data_set(&root.join("W10_20H2_Pro_19042.1052"));
+    copy_sample_data_set(&root.join("W10_20H2_Pro_19042.631"));
+
+    let schema = generate_schema_from_vanilla(root)
+        .expect("Error generating schema.");
+    let index = Index::create_in_ram(schema);
+    let mut writer = WindowRefIndexWriter::from_index(root, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    writer.index().expect("Error indexing.");
+    let reader = WindowsRefIndexReader::try_from(index)
+        .expect("Error creating reader.");
+    let filter = LookupFilter::default();
+
+    // Ordered by UBR, not by the data set path
+    let history = reader.get_build_history(&WindowsPath::new(r"windows\system32\cmd.exe"), &filter)
+        .expect("Error getting history.");
+    assert_eq!(history.first_seen.as_deref(), Some("19042.631"));
+    assert_eq!(history.last_seen.as_deref(), Some("19042.1052"));
+    assert_eq!(history.builds.len(), 2);
+    assert_eq!(history.builds[0].data_set_path.as_deref(), Some("W10_20H2_Pro_19042.631"));
+    assert_eq!(history.builds[0].os_build, Some(19042));
+    assert_eq!(history.builds[0].length, Some(289792));
+    assert_eq!(history.builds[0].md5.as_deref(), Some("17EB8E0DD3D4211CDE089DFD9A238891"));
+
+    // A file name in any directory
+    let history = reader.get_build_history(&WindowsPath::new("cmd.exe"), &filter)
+        .expect("Error getting history.");
+    assert_eq!(history.builds.len(), 4);
+
+    let history = reader.get_build_history(&WindowsPath::new(r"windows\cmd.exe"), &filter)
+        .expect("Error getting history.");
+    assert!(history.builds.is_empty());
+    assert_eq!(history.first_seen, None);
+    assert!(!history.truncated);
+
+    // Full paths in the root of a volume are not file names in any directory
+    let history = reader.get_build_history(&WindowsPath::new(r"C:\cmd.exe"), &filter)
+        .expect("Error getting history.");
+    assert!(history.builds.is_empty());
+    let history = reader.get_build_history(&WindowsPath::new(r"C:\PsExec_IgnoreThisFile_ResearchTool.exe"), &filter)
+        .expect("Error getting history.");
+    assert_eq!(history.builds.len(), 2);
+}
+
+
+#[test]
+fn test_build_history_gaps() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let root = temp_dir.path();
+    for build in ["631", "800", "1052", "1100"] {
+        copy_sample_data_set(&root.join(format!("W10_20H2_Pro_19042.{}", build)));
+    }
+    // A newer build of another edition without cmd.exe
+    let other_edition = root.join("W10_20H2_Ent_19042.1200");
+    copy_sample_data_set(&other_edition);
+    let csv_path = other_edition.join("W10_Pro_20H2_19042.csv");
+    let content = std::fs::read_to_string(&csv_path).expect("Error reading csv.");
+    let content: Vec<&str> = content.split("\r\n")
+        .filter(|line| !line.contains(r"\cmd.exe"))
+        .collect();
+    std::fs::write(&csv_path, content.join("\r\n")).expect("Error writing csv.");
+    // The file is missing from the 800 and 1100 builds
+    for build in ["800", "1100"] {
+        let csv_path = root.join(format!("W10_20H2_Pro_19042.{}", build)).join("W10_Pro_20H2_19042.csv");
+        let content = std::fs::read_to_string(&csv_path).expect("Error reading csv.");
+        let content: Vec<&str> = content.split("\r\n")
+            .filter(|line| !line.contains(r"C:\Windows\System32\cmd.exe"))
+            .collect();
+        std::fs::write(&csv_path, content.join("\r\n")).expect("Error writing csv.");
+    }
+
+    let schema = generate_schema_from_vanilla(root)
+        .expect("Error generating schema.");
+    let index = Index::create_in_ram(schema);
+    let mut writer = WindowRefIndexWriter::from_index(root, index.clone(), 50_000_000)
+        .expect("Error creating WindowRefIndexWriter.");
+    writer.index().expect("Error indexing.");
+    let reader = WindowsRefIndexReader::try_from(index)
+        .expect("Error creating reader.");
+
+    let history = reader.get_build_history(&WindowsPath::new(r"windows\system32\cmd.exe"), &LookupFilter::default())
+        .expect("Error getting history.");
+    assert_eq!(history.first_seen.as_deref(), Some("19042.631"));
+    assert_eq!(history.last_seen.as_deref(), Some("19042.1052"));
+    assert_eq!(history.missing_builds, vec!["19042.800"]);
+    assert!(history.removed);
+    assert!(!history.truncated);
+
+    // Still in every build of its edition
+    let history = reader.get_build_history(&WindowsPath::new(r"windows\syswow64\cmd.exe"), &LookupFilter::default())
+        .expect("Error getting history.");
+    assert_eq!(history.builds.len(), 4);
+    assert!(history.missing_builds.is_empty());
+    assert!(!history.removed);
+}
+
+
+#[test]
+fn test_glob_search() {
+    let reader = get_sample_reader();
+    let filter = LookupFilter::default();
+    let names = |glob: &str| -> Vec<String> {
+        let mut names: Vec<String> = reader.search_glob(glob, &filter, 100, 0)
+            .expect("Error searching.")
+            .hits
+            .iter()
+            .map(|h| h["FullName"][0].as_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+
+    assert_eq!(names(r"C:\Windows\System32\drivers\*.sys"), vec![r"Windows\System32\drivers\ntfs.sys"]);
+    assert_eq!(names("windows/system32/*.exe").len(), 4);
+    assert_eq!(names("*HOST*.exe"), vec![r"Windows\SysWOW64\svchost.exe", r"Windows\System32\svchost.exe"]);
+    assert_eq!(names("cmd.ex?").len(), 2);
+    assert!(names("cmd").is_empty());
+    // Regex characters are matched as is
+    assert!(names("cmd.(exe)").is_empty());
+
+    // Paging
+    let page = reader.search_glob("*.exe", &filter, 2, 0).expect("Error searching.");
+    assert_eq!(page.total, 10);
+    assert_eq!(page.hits.len(), 2);
+    let last = reader.search_glob("*.exe", &filter, 2, 9).expect("Error searching.");
+    assert_eq!(last.hits.len(), 1);
+
+    let filter = LookupFilter { max_build: Some(18363), ..Default::default() };
+    assert_eq!(reader.search_glob("*.exe", &filter, 10, 0).expect("Error searching.").total, 0);
+
+    // Pages past the search window (or that overflow) are rejected
+    assert!(reader.search_glob("*.exe", &filter, 10, MAX_SEARCH_WINDOW).is_err());
+    assert!(reader.search_glob("*.exe", &filter, 10, usize::MAX).is_err());
+
+    assert_eq!(glob_to_regex(r"Windows\*.d?l"), r"windows\\.*\.d.l");
+}
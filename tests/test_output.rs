@@ -0,0 +1,72 @@
+mod common;
+
+use serde_json::{json, Value};
+use winvanilla::output::{get_cell_value, RecordFormat, RecordWriter};
+use winvanilla::vanilla::WinFileListIterator;
+
+
+/// Write the records of the samples with the given columns
+fn write_samples(format: RecordFormat, columns: &[&str], exclude: Option<Vec<String>>) -> String {
+    let columns = columns.iter().map(|c| c.to_string()).collect();
+    let mut writer = RecordWriter::new(Vec::new(), format, columns)
+        .expect("Error creating writer.");
+
+    for (_location, file_list) in WinFileListIterator::from_path(common::SAMPLES) {
+        let mut records = file_list.into_iter()
+            .expect("Error reading file list.");
+        if let Some(exclude) = &exclude {
+            records.set_exclude_list(exclude.clone());
+        }
+        for record in records {
+            writer.write_record(&record).expect("Error writing record.");
+        }
+    }
+
+    let output = writer.finish().expect("Error finishing writer.");
+    String::from_utf8(output).expect("Output is not utf8.")
+}
+
+
+#[test]
+fn test_record_formats() {
+    let output = write_samples(RecordFormat::Jsonl, &["Name", "Length", "Sddl"], None);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 16);
+    // Sddl is excluded by default
+    assert_eq!(lines[5], r#"{"Length":"289792","Name":"cmd.exe"}"#);
+
+    let output = write_samples(RecordFormat::Jsonl, &["Name", "Sddl"], Some(Vec::new()));
+    let value: Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert!(value["Sddl"].as_str().unwrap().starts_with("O:BAG:"));
+
+    let output = write_samples(RecordFormat::Csv, &["FullName", "Hotfixes", "Missing"], None);
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines[0], "FullName,Hotfixes,Missing");
+    assert_eq!(
+        lines[6],
+        r"C:\Windows\System32\cmd.exe,KB4578968|KB4562830|KB4570334|KB4580325|KB4586864|KB5005716|KB4594440,"
+    );
+
+    let output = write_samples(RecordFormat::Tsv, &["Name", "OsBuild"], None);
+    assert_eq!(output.lines().nth(6), Some("cmd.exe\t19042"));
+
+    let output = write_samples(RecordFormat::Columns, &["Name", "OsBuild"], None);
+    let value: Value = serde_json::from_str(&output).expect("Output is not json.");
+    assert_eq!(value["Name"].as_array().unwrap().len(), 16);
+    assert_eq!(value["Name"][5], json!("cmd.exe"));
+    assert_eq!(value["OsBuild"][5], json!(19042));
+}
+
+
+#[test]
+fn test_record_format_helpers() {
+    assert_eq!("TSV".parse::<RecordFormat>(), Ok(RecordFormat::Tsv));
+    assert_eq!("columns".parse::<RecordFormat>(), Ok(RecordFormat::Columns));
+    assert!("parquet".parse::<RecordFormat>().is_err());
+    assert_eq!(RecordFormat::Jsonl.to_string(), "jsonl");
+
+    assert_eq!(get_cell_value(&json!(null)), "");
+    assert_eq!(get_cell_value(&json!("a")), "a");
+    assert_eq!(get_cell_value(&json!(12)), "12");
+    assert_eq!(get_cell_value(&json!(["a", "b"])), "a|b");
+}
@@ -0,0 +1,295 @@
+mod common;
+
+use rocket::{catchers, routes};
+use rocket::http::{ContentType, Status};
+use rocket::local::blocking::Client;
+use serde_json::Value;
+use common::get_sample_reader;
+use winvanilla::service::error::{default_catcher, not_found, unprocessable_entity};
+use winvanilla::service::hash::{lookup_hash, lookup_hash_batch};
+use winvanilla::service::path::{history_full_name, known_full_name, similar_file_name, verdict_full_name};
+use winvanilla::service::search::search_glob;
+
+
+fn get_client() -> Client {
+    let rocket = rocket::build()
+        .manage(get_sample_reader())
+        .mount("/", routes![
+            known_full_name, history_full_name, similar_file_name,
+            verdict_full_name, lookup_hash, lookup_hash_batch, search_glob
+        ])
+        .register("/", catchers![not_found, unprocessable_entity, default_catcher]);
+
+    Client::tracked(rocket).expect("Error creating client.")
+}
+
+
+fn post_json(client: &Client, uri: &'static str, body: &str) -> (Status, Value) {
+    let response = client.post(uri)
+        .header(ContentType::JSON)
+        .body(body)
+        .dispatch();
+    let status = response.status();
+    let value = response.into_json::<Value>()
+        .expect("Response is not json.");
+    (status, value)
+}
+
+
+#[test]
+fn test_error_responses() {
+    let client = get_client();
+
+    // A path without a file name
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\"}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "InvalidInput");
+
+    // An unhandled hash length
+    let (status, value) = post_json(&client, "/api/v1/lookup/hash", r#"{"value": "abc"}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "InvalidInput");
+    assert_eq!(value["detail"], "Unhandled hash type with length: 3");
+
+    // A body that does not match the lookup structure
+    let (status, value) = post_json(&client, "/api/v1/lookup/hash", r#"{"hash": "abc"}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "UnprocessableEntity");
+
+    // Unknown route
+    let (status, value) = post_json(&client, "/api/v1/unknown", "{}");
+    assert_eq!(status, Status::NotFound);
+    assert_eq!(value["error"], "NotFound");
+
+    // Successful lookups are unaffected
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\System32\\cmd.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownName"], true);
+    assert_eq!(value["KnownPath"], true);
+}
+
+
+#[test]
+fn test_lookup_filters() {
+    let client = get_client();
+
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\System32\\cmd.exe", "min_build": 19041}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownPath"], true);
+
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\System32\\cmd.exe", "os_version": "10.0.18363 N/A Build 18363"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownName"], false);
+    assert_eq!(value["KnownPath"], false);
+
+    let (status, value) = post_json(&client, "/api/v1/lookup/hash", r#"{"value": "6176656c4d6a215bd670d5bd63d35b59", "min_build": 19043, "max_build": 19042}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "InvalidInput");
+}
+
+
+#[test]
+fn test_lookup_hash_batch() {
+    let client = get_client();
+
+    let body = r#"{"values": [
+        "C590A84B8C72CF18F35AE166F815C9DF",
+        "c590a84b8c72cf18f35ae166f815c9df",
+        "0000000000000000000000000000000000000000000000000000000000000000",
+        "abc"
+    ]}"#;
+    let (status, value) = post_json(&client, "/api/v1/lookup/hash/batch", body);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownCount"], 1);
+    assert_eq!(value["UnknownCount"], 1);
+    assert_eq!(value["InvalidCount"], 1);
+
+    // Duplicates are removed and the order of the values is kept
+    let results = value["Results"].as_array().unwrap();
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0]["Value"], "c590a84b8c72cf18f35ae166f815c9df");
+    assert_eq!(results[0]["Known"], true);
+    assert_eq!(results[0]["Truncated"], false);
+    assert!(!results[0]["FullName"].as_array().unwrap().is_empty());
+    assert_eq!(results[1]["Known"], false);
+    assert!(results[1]["FullName"].as_array().unwrap().is_empty());
+    assert_eq!(results[2]["Value"], "abc");
+    assert_eq!(results[2]["Known"], Value::Null);
+    assert_eq!(results[2]["Error"], "Unhandled hash type with length: 3");
+
+    // Batches are limited to 1000 values
+    let values = vec![format!("{:032x}", 0); 1001];
+    let body = serde_json::json!({"values": values}).to_string();
+    let response = client.post("/api/v1/lookup/hash/batch")
+        .header(ContentType::JSON)
+        .body(body)
+        .dispatch();
+    assert_eq!(response.status(), Status::UnprocessableEntity);
+    let value = response.into_json::<Value>().expect("Response is not json.");
+    assert_eq!(value["error"], "InvalidInput");
+    assert_eq!(value["detail"], "batch of 1001 values is greater than 1000");
+}
+
+
+#[test]
+fn test_build_history() {
+    let client = get_client();
+
+    let (status, value) = post_json(&client, "/api/v1/history/fullname", r#"{"value": "C:/Windows/System32/cmd.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["FirstSeen"], "19042.631");
+    assert_eq!(value["LastSeen"], "19042.631");
+    assert_eq!(value["Builds"].as_array().unwrap().len(), 1);
+    assert_eq!(value["Builds"][0]["SHA256"], "A20F0A589E2A8EB5F070E6EE7D7ED6F677D61A52882E5282F97BB177E9876649");
+    assert_eq!(value["Builds"][0]["Length"], 289792);
+    assert_eq!(value["Truncated"], false);
+    assert_eq!(value["Removed"], false);
+
+    let (status, value) = post_json(&client, "/api/v1/history/fullname", r#"{"value": "C:/Windows/System32/cmd.exe", "max_build": 18363}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["FirstSeen"], Value::Null);
+    assert!(value["Builds"].as_array().unwrap().is_empty());
+}
+
+
+#[test]
+fn test_search() {
+    let client = get_client();
+
+    let (status, value) = post_json(&client, "/api/v1/search", r#"{"value": "C:\\Windows\\System32\\*.exe", "limit": 3, "offset": 1}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["Total"], 4);
+    assert_eq!(value["Offset"], 1);
+    assert_eq!(value["Hits"].as_array().unwrap().len(), 3);
+
+    let (status, value) = post_json(&client, "/api/v1/search", r#"{"value": "*.dll"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["Limit"], 100);
+
+    let (status, value) = post_json(&client, "/api/v1/search", r#"{"value": "*.dll", "limit": 5000}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "InvalidInput");
+
+    let (status, value) = post_json(&client, "/api/v1/search", r#"{"value": "*", "offset": 10000000000000}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+    assert_eq!(value["error"], "InvalidInput");
+}
+
+
+#[test]
+fn test_similar_names() {
+    let client = get_client();
+
+    let (status, value) = post_json(&client, "/api/v1/similar/name", r#"{"value": "svch0st.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["SimilarNames"][0]["Name"], "svchost.exe");
+    assert_eq!(value["SimilarNames"][0]["Confusable"], true);
+
+    let (status, _value) = post_json(&client, "/api/v1/similar/name", r#"{"value": "svch0st.exe", "limit": 1001}"#);
+    assert_eq!(status, Status::UnprocessableEntity);
+
+    // Unknown names get the similar names as a hint
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\System32\\lsasss.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownName"], false);
+    assert_eq!(value["SimilarNames"][0]["Name"], "lsass.exe");
+
+    let (_status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\System32\\lsass.exe"}"#);
+    assert_eq!(value["KnownName"], true);
+    assert!(value.get("SimilarNames").is_none());
+
+    // A trailing space is part of the name
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\explorer.exe "}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownName"], false);
+    assert_eq!(value["SimilarNames"][0]["Name"], "explorer.exe");
+    assert_eq!(value["SimilarNames"][0]["Confusable"], true);
+}
+
+
+#[test]
+fn test_device_paths() {
+    let client = get_client();
+
+    let paths = [
+        r#"{"value": "\\\\?\\C:\\Windows\\System32\\cmd.exe"}"#,
+        r#"{"value": "\\Device\\HarddiskVolume3\\Windows\\System32\\cmd.exe"}"#,
+        r#"{"value": "\\\\?\\GLOBALROOT\\Device\\HarddiskVolumeShadowCopy1\\Windows\\System32\\cmd.exe"}"#,
+        r#"{"value": "\\\\WKS01\\C$\\Windows\\System32\\cmd.exe"}"#,
+    ];
+    for body in paths {
+        let (status, value) = post_json(&client, "/api/v1/known/fullname", body);
+        assert_eq!(status, Status::Ok);
+        assert_eq!(value["KnownName"], true, "{}", body);
+        assert_eq!(value["KnownPath"], true, "{}", body);
+    }
+
+    let (status, value) = post_json(&client, "/api/v1/history/fullname", r#"{"value": "\\Device\\HarddiskVolume3\\Windows\\System32\\cmd.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert!(!value["Builds"].as_array().unwrap().is_empty());
+}
+
+
+#[test]
+fn test_templated_paths() {
+    let client = get_client();
+
+    // The reference was built with the account `user`
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Users\\jdoe\\AppData\\Local\\Microsoft\\OneDrive\\OneDriveStandaloneUpdater.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownName"], true);
+    assert_eq!(value["KnownPath"], true);
+
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "%LOCALAPPDATA%\\Microsoft\\OneDrive\\OneDriveStandaloneUpdater.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownPath"], true);
+
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "%SystemRoot%\\system32\\svchost.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownPath"], true);
+}
+
+
+#[test]
+fn test_redirected_paths() {
+    let client = get_client();
+
+    // notepad.exe is only in System32
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\SysWOW64\\notepad.exe"}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownPath"], false);
+    assert!(value.get("MatchedPath").is_none());
+
+    let (status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\SysWOW64\\notepad.exe", "redirect": true}"#);
+    assert_eq!(status, Status::Ok);
+    assert_eq!(value["KnownPath"], true);
+    assert_eq!(value["MatchedPath"], r"Windows\System32");
+
+    let (_status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Windows\\Sysnative\\cmd.exe", "redirect": true}"#);
+    assert_eq!(value["MatchedPath"], r"Windows\System32");
+
+    let (_status, value) = post_json(&client, "/api/v1/known/fullname", r#"{"value": "C:\\Program Files\\Internet Explorer\\iexplore.exe", "redirect": true}"#);
+    assert_eq!(value["KnownPath"], true);
+    assert_eq!(value["MatchedPath"], r"Program Files (x86)\Internet Explorer");
+
+    // The 32-bit cmd.exe reported at System32 matches SysWOW64 by hash
+    let body = r#"{"value": "C:\\Windows\\System32\\cmd.exe", "hash": "A1FD6AE2F9D397D4F93D3257C027C634", "redirect": true}"#;
+    let (_status, value) = post_json(&client, "/api/v1/known/fullname", body);
+    assert_eq!(value["KnownHash"], true);
+    assert_eq!(value["MatchedPath"], r"Windows\SysWOW64");
+
+    let (_status, value) = post_json(&client, "/api/v1/verdict/fullname", body);
+    assert_eq!(value["Verdict"], "Vanilla");
+    assert_eq!(value["MatchedPath"], r"Windows\SysWOW64");
+
+    let body = r#"{"value": "C:\\Windows\\System32\\cmd.exe", "hash": "A1FD6AE2F9D397D4F93D3257C027C634"}"#;
+    let (_status, value) = post_json(&client, "/api/v1/verdict/fullname", body);
+    assert_eq!(value["Verdict"], "HashMismatch");
+    assert!(value.get("MatchedPath").is_none());
+
+    // Unknown hashes are not matched anywhere
+    let body = r#"{"value": "C:\\Windows\\System32\\cmd.exe", "hash": "00000000000000000000000000000000", "redirect": true}"#;
+    let (_status, value) = post_json(&client, "/api/v1/verdict/fullname", body);
+    assert_eq!(value["Verdict"], "HashMismatch");
+    assert_eq!(value["MatchedPath"], r"Windows\System32");
+}
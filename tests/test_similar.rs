@@ -0,0 +1,97 @@
+mod common;
+
+use common::get_sample_reader;
+use winvanilla::similar::{edit_distance, get_similar_name, normalize_confusables, SimilarNameIndex};
+
+
+#[test]
+fn test_normalize_confusables() {
+    assert_eq!(normalize_confusables("svch0st.exe"), normalize_confusables("svchost.exe"));
+    assert_eq!(normalize_confusables("LSASS.EXE"), normalize_confusables("lsass.exe"));
+    assert_eq!(normalize_confusables("explorer .exe "), normalize_confusables("explorer.exe"));
+    // Cyrillic 'ѕ' and 'о', fullwidth 'ｅ' and a zero width space
+    assert_eq!(normalize_confusables("\u{0455}vch\u{043e}st.\u{ff45}xe\u{200b}"), normalize_confusables("svchost.exe"));
+    assert_eq!(normalize_confusables("expl0rer.exe"), "explorer.exe");
+    assert_eq!(normalize_confusables("rnsmpeng.exe"), normalize_confusables("msmpeng.exe"));
+    // Cyrillic 'і' looks like 'i', but 'i' and 'l' are different letters
+    assert_eq!(normalize_confusables("ma\u{0456}l.dll"), "mail.dll");
+    assert_ne!(normalize_confusables("mail.dll"), normalize_confusables("mall.dll"));
+}
+
+
+#[test]
+fn test_edit_distance() {
+    assert_eq!(edit_distance("", ""), 0);
+    assert_eq!(edit_distance("lsass.exe", "lsass.exe"), 0);
+    assert_eq!(edit_distance("lsasss.exe", "lsass.exe"), 1);
+    assert_eq!(edit_distance("scvhost.exe", "svchost.exe"), 1);
+    assert_eq!(edit_distance("svchost.exe", "svch0st.exe"), 1);
+    assert_eq!(edit_distance("cmd.exe", ""), 7);
+    assert_eq!(edit_distance("kitten", "sitting"), 3);
+}
+
+
+#[test]
+fn test_similar_name() {
+    let similar = get_similar_name("svch0st.exe", "svchost.exe")
+        .expect("Names are similar.");
+    assert!(similar.confusable);
+    assert_eq!(similar.score, 1.0);
+    assert_eq!(similar.distance, 1);
+
+    let similar = get_similar_name("lsasss.exe", "lsass.exe")
+        .expect("Names are similar.");
+    assert!(!similar.confusable);
+    assert_eq!(similar.distance, 1);
+    assert!(similar.score > 0.8 && similar.score < 1.0);
+
+    // Equal names and names that are too different
+    assert_eq!(get_similar_name("Cmd.exe", "cmd.exe"), None);
+    assert_eq!(get_similar_name("notepad.exe", "cmd.exe"), None);
+    assert_eq!(get_similar_name("a.exe", "cmd.exe"), None);
+}
+
+
+#[test]
+fn test_similar_name_index() {
+    let index = SimilarNameIndex::from_names(["svchost.exe", "SVCHOST.EXE", "lsass.exe", "explorer.exe", "a.exe"]);
+    assert_eq!(index.len(), 4);
+
+    let similar = index.get_similar_names("svch0st.exe", 5);
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0], get_similar_name("svch0st.exe", "svchost.exe").unwrap());
+
+    // Names too far apart in length are not compared
+    assert!(index.get_similar_names("explorer.exe.exe", 5).is_empty());
+    assert!(index.get_similar_names("lsass.exe", 5).is_empty());
+    assert!(SimilarNameIndex::default().get_similar_names("lsass.exe", 5).is_empty());
+}
+
+
+#[test]
+fn test_similar_names_lookup() {
+    let reader = get_sample_reader();
+
+    let similar = reader.get_similar_names("svch0st.exe", 5)
+        .expect("Error getting similar names.");
+    assert_eq!(similar[0].name, "svchost.exe");
+    assert!(similar[0].confusable);
+
+    let similar = reader.get_similar_names("explorer .exe", 5)
+        .expect("Error getting similar names.");
+    assert_eq!(similar[0].name, "explorer.exe");
+
+    let similar = reader.get_similar_names("lsasss.exe", 5)
+        .expect("Error getting similar names.");
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].name, "lsass.exe");
+
+    // Known names are not similar to themselves
+    assert!(reader.get_similar_names("cmd.exe", 5).unwrap().iter().all(|s| s.name != "cmd.exe"));
+    assert!(reader.get_similar_names("totally_unrelated.bin", 5).unwrap().is_empty());
+
+    // The known names are reused for the next lookups
+    let similar = reader.get_similar_names("svch0st.exe", 5)
+        .expect("Error getting similar names.");
+    assert_eq!(similar[0].name, "svchost.exe");
+}
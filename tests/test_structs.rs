@@ -1,14 +1,154 @@
-use winvanilla::vanilla::WindowsInfo;
+use chrono::{TimeZone, Utc};
+use tempfile::TempDir;
+use winvanilla::vanilla::{
+    DataSetInfo, FileAttributes, SystemInfo, VanillaFileRecord, WindowsFileList, WindowsInfo,
+    WinFileListIterator
+};
 
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_win_info() {
     let info = WindowsInfo::from_path("samples/W10_20H2_Pro_19042.631/SystemInfo_W10_Pro_20H2_19042.txt")
         .expect("Error parsing SystemInfo file.");
 
     assert_eq!(info.name, "Microsoft Windows 10 Pro");
     assert_eq!(info.version, "10.0.19042 N/A Build 19042");
+    assert_eq!(info.build_type.as_deref(), Some("Multiprocessor Free"));
+    assert_eq!(info.system_type.as_deref(), Some("x64-based PC"));
+    assert_eq!(info.original_install_date.as_deref(), Some("11/19/2021, 11:25:04 PM"));
+    assert_eq!(info.system_locale.as_deref(), Some("en-us"));
+    assert_eq!(info.time_zone.as_deref(), Some("(UTC-05:00) Eastern Time (US & Canada)"));
+    assert_eq!(info.hotfixes, vec![
+        "KB4578968", "KB4562830", "KB4570334", "KB4580325",
+        "KB4586864", "KB5005716", "KB4594440"
+    ]);
 
     let error = WindowsInfo::from_path("samples/W10_20H2_Pro_19042.631/W10_Pro_20H2_19042.csv");
     assert_eq!(error.is_err(), true);
-}
\ No newline at end of file
+}
+
+
+#[test]
+fn test_system_info_continuation() {
+    let info = SystemInfo::from_path("samples/W10_20H2_Pro_19042.631/SystemInfo_W10_Pro_20H2_19042.txt")
+        .expect("Error parsing SystemInfo file.");
+
+    // Keys that contain a colon
+    assert_eq!(info.get_value("Virtual Memory: Max Size"), Some("3,199 MB"));
+    // Keys without a value
+    assert_eq!(info.get_value("Registered Organization"), None);
+
+    // Nested continuation blocks stay with their entry
+    let nics = info.get("Network Card(s)").expect("No Network Card(s) entry.");
+    assert_eq!(nics.value, "2 NIC(s) Installed.");
+    assert!(nics.continuation.contains(&"Connection Name: Ethernet0".to_string()));
+    assert_eq!(
+        info.get_value("Hyper-V Requirements"),
+        Some("A hypervisor has been detected. Features required for Hyper-V will not be displayed.")
+    );
+}
+
+
+#[test]
+fn test_typed_records() {
+    let file_list = WindowsFileList::from_folder("samples/W10_20H2_Pro_19042.631")
+        .expect("Error creating WindowsFileList.");
+    let records = file_list.into_record_iter()
+        .expect("Error creating VanillaFileRecordIterator.")
+        .collect::<Result<Vec<VanillaFileRecord>, String>>()
+        .expect("Error reading records.");
+    assert_eq!(records.len(), 16);
+
+    let record = &records[0];
+    assert_eq!(record.full_name.to_string(), r"C:\PsExec_IgnoreThisFile_ResearchTool.exe");
+    assert_eq!(record.full_name.relative_path(), "PsExec_IgnoreThisFile_ResearchTool.exe");
+    assert_eq!(record.directory_name.relative_path(), "");
+    assert_eq!(record.length, 834936);
+    assert_eq!(record.creation_time, Some(Utc.with_ymd_and_hms(2021, 11, 19, 23, 59, 24).unwrap()));
+    assert_eq!(record.md5.as_deref(), Some("C590A84B8C72CF18F35AE166F815C9DF"));
+
+    // Files that could not be hashed
+    let ntuser = records.iter()
+        .find(|r| r.name == "NTUSER.DAT")
+        .expect("NTUSER.DAT not found.");
+    assert_eq!(ntuser.md5, None);
+    assert_eq!(ntuser.sha256, None);
+    assert!(ntuser.attributes.contains(FileAttributes::HIDDEN | FileAttributes::NOT_CONTENT_INDEXED));
+    assert!(!ntuser.attributes.contains(FileAttributes::SYSTEM));
+    assert_eq!(ntuser.attributes.to_string(), "Hidden, Archive, NotContentIndexed");
+}
+
+
+#[test]
+fn test_typed_record_errors() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    std::fs::copy(
+        "samples/W10_20H2_Pro_19042.631/SystemInfo_W10_Pro_20H2_19042.txt",
+        temp_dir.path().join("SystemInfo_W10_Pro_20H2_19042.txt")
+    ).expect("Error copying SystemInfo file.");
+    std::fs::write(
+        temp_dir.path().join("file_list.csv"),
+        "\"DirectoryName\",\"Name\",\"FullName\",\"Length\",\"CreationTimeUtc\",\"Attributes\"\r\n\
+        \"C:\\\",\"a.txt\",\"C:\\a.txt\",\"12\",\"7/10/2015 11:00:41 AM\",\"Archive\"\r\n\
+        \"C:\\\",\"b.txt\",\"C:\\b.txt\",\"large\",\"7/10/2015 11:00:41 AM\",\"Archive\"\r\n\
+        \"C:\\\",\"c.txt\",\"C:\\c.txt\",\"12\",\"10.07.2015 11:00:41\",\"Archive\"\r\n\
+        \"C:\\\",\"d.txt\",\"C:\\d.txt\",\"12\",\"\",\"Archive\"\r\n"
+    ).expect("Error writing file list.");
+
+    let file_list = WindowsFileList::from_folder(temp_dir.path())
+        .expect("Error creating WindowsFileList.");
+    let results = file_list.into_record_iter()
+        .expect("Error creating VanillaFileRecordIterator.")
+        .collect::<Vec<_>>();
+
+    assert_eq!(results.len(), 4);
+    assert!(results[0].as_ref().unwrap().creation_time.is_some());
+    let error = results[1].as_ref().expect_err("Malformed Length should error.");
+    assert!(error.starts_with("row 2:"), "{}", error);
+    // Timestamps that can't be parsed (or are empty) don't fail the row
+    assert_eq!(results[2].as_ref().unwrap().creation_time, None);
+    assert_eq!(results[3].as_ref().unwrap().creation_time, None);
+}
+
+
+#[test]
+fn test_data_set_info() {
+    let info = DataSetInfo::new("reference", "reference/Windows10/20H2/W10_20H2_Pro_19042.631");
+    assert_eq!(info, DataSetInfo {
+        path: "Windows10/20H2/W10_20H2_Pro_19042.631".to_string(),
+        edition: Some("Pro".to_string()),
+        architecture: Some("x64".to_string()),
+        release: Some("20H2".to_string()),
+        full_build: Some("19042.631".to_string()),
+    });
+
+    // Older data sets have the collection date before the build
+    let info = DataSetInfo::new("reference", "reference/Windows10/1507/W10_1507_Pro_20150729_10240");
+    assert_eq!(info.edition.as_deref(), Some("Pro"));
+    assert_eq!(info.release.as_deref(), Some("1507"));
+    assert_eq!(info.full_build.as_deref(), Some("10240"));
+
+    let info = DataSetInfo::new("reference", "reference/Windows11/21H2/W11_21H2_Pro_ARM_22000.376");
+    assert_eq!(info.edition.as_deref(), Some("Pro"));
+    assert_eq!(info.architecture.as_deref(), Some("ARM"));
+    assert_eq!(info.full_build.as_deref(), Some("22000.376"));
+
+    let info = DataSetInfo::new("reference", "reference/Other/my_data_set");
+    assert_eq!(info.path, "Other/my_data_set");
+    assert_eq!(info.edition, None);
+    assert_eq!(info.architecture, None);
+
+    // Records of a file list carry the data set values
+    let (_location, file_list) = WinFileListIterator::from_path("samples")
+        .next()
+        .expect("No file list in samples.");
+    let record = file_list.into_iter()
+        .expect("Error reading file list.")
+        .next()
+        .expect("No records.");
+    assert_eq!(record["DataSetPath"], "W10_20H2_Pro_19042.631");
+    assert_eq!(record["Architecture"], "x64");
+    assert_eq!(record["Release"], "20H2");
+    assert_eq!(record["FullBuild"], "19042.631");
+}
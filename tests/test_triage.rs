@@ -0,0 +1,248 @@
+mod common;
+
+use tempfile::TempDir;
+use common::get_sample_reader;
+use winvanilla::index::LookupFilter;
+use winvanilla::jsonl::{FieldMapping, JsonlRecordSource};
+use winvanilla::mftecmd::MftECmdRecordIterator;
+use winvanilla::record::{RecordSource, TriageRecord};
+use winvanilla::triage::{ReportFormat, ReportWriter, Triage, TriageSummary};
+use winvanilla::vanilla::{CsvFileRecordIterator, WindowsFileList};
+use winvanilla::verdict::Verdict;
+
+
+#[test]
+fn test_triage_file_list() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let file_list = temp_dir.path().join("host.csv");
+    std::fs::write(
+        &file_list,
+        "\"DirectoryName\",\"Name\",\"FullName\",\"Length\",\"CreationTimeUtc\",\"Attributes\",\"MD5\"\r\n\
+        \"C:\\Windows\\System32\",\"cmd.exe\",\"C:\\Windows\\System32\\cmd.exe\",\"289792\",\"7/10/2015 11:00:41 AM\",\"Archive\",\"\"\r\n\
+        \"C:\\Users\\Public\",\"svchost.exe\",\"C:\\Users\\Public\\svchost.exe\",\"51712\",\"7/10/2015 11:00:41 AM\",\"Archive\",\"\"\r\n\
+        \"C:\\Windows\\System32\",\"cmd.exe\",\"C:\\Windows\\System32\\cmd.exe\",\"289792\",\"7/10/2015 11:00:41 AM\",\"Archive\",\"D760CBE8ADA11F064A08D60AF62FD9F2\"\r\n\
+        \"C:\\Windows\\System32\",\"svch0st.exe\",\"C:\\Windows\\System32\\svch0st.exe\",\"51712\",\"7/10/2015 11:00:41 AM\",\"Archive\",\"\"\r\n\
+        \"C:\\Windows\",\"bad.exe\",\"C:\\Windows\\bad.exe\",\"large\",\"7/10/2015 11:00:41 AM\",\"Archive\",\"\"\r\n"
+    ).expect("Error writing file list.");
+
+    let reader = get_sample_reader();
+    let mut triage = Triage::new(&reader, LookupFilter::default());
+
+    let mut summary = TriageSummary::default();
+    let mut output = Vec::new();
+    let mut report = ReportWriter::new(&mut output, ReportFormat::Csv)
+        .expect("Error creating report.");
+    for result in CsvFileRecordIterator::from_path(&file_list).expect("Error opening file list.") {
+        let record: TriageRecord = match result {
+            Ok(r) => r.into(),
+            Err(_) => {
+                summary.errors += 1;
+                continue;
+            }
+        };
+        let triage_result = triage.triage_record(&record)
+            .expect("Error triaging record.");
+        summary.add(triage_result.verdict.verdict);
+        report.write_result(&triage_result).expect("Error writing report.");
+    }
+    report.flush().expect("Error flushing report.");
+    drop(report);
+
+    assert_eq!(summary, TriageSummary {
+        vanilla: 1,
+        masquerade_candidate: 1,
+        hash_mismatch: 1,
+        unknown: 1,
+        errors: 1
+    });
+
+    let output = String::from_utf8(output).expect("Report is not utf8.");
+    let lines = output.lines().collect::<Vec<&str>>();
+    assert_eq!(lines.len(), 5);
+    assert_eq!(lines[0], "FullName,Length,Hash,Verdict,KnownName,KnownPath,KnownHash,KnownDirectories");
+    assert_eq!(lines[2], r"C:\Users\Public\svchost.exe,51712,,MasqueradeCandidate,true,false,,Windows\System32|Windows\SysWOW64");
+}
+
+
+#[test]
+fn test_triage_record_jsonl() {
+    let reader = get_sample_reader();
+    let record = TriageRecord {
+        full_name: r"C:\Windows\System32\cmd.exe".to_string(),
+        directory: r"C:\Windows\System32".to_string(),
+        name: "cmd.exe".to_string(),
+        ..Default::default()
+    };
+
+    let result = Triage::new(&reader, LookupFilter::default())
+        .triage_record(&record)
+        .expect("Error triaging record.");
+    assert_eq!(result.verdict.verdict, Verdict::Vanilla);
+
+    let mut output = Vec::new();
+    let mut report = ReportWriter::new(&mut output, ReportFormat::Jsonl)
+        .expect("Error creating report.");
+    report.write_result(&result).expect("Error writing report.");
+    drop(report);
+
+    let value: serde_json::Value = serde_json::from_slice(&output)
+        .expect("Report line is not json.");
+    assert_eq!(value["FullName"], r"C:\Windows\System32\cmd.exe");
+    assert_eq!(value["Verdict"], "Vanilla");
+    assert_eq!(value["KnownPath"], true);
+}
+
+
+#[test]
+fn test_mftecmd_records() {
+    let temp_dir = TempDir::new().expect("Error creating temp dir.");
+    let file_list = temp_dir.path().join("mft.csv");
+    std::fs::write(
+        &file_list,
+        "EntryNumber,SequenceNumber,InUse,ParentEntryNumber,ParentSequenceNumber,ParentPath,FileName,Extension,FileSize,IsDirectory,HasAds,IsAds,Created0x10\r\n\
+        100,1,True,5,5,.\\Windows\\System32,cmd.exe,.exe,289792,False,False,False,2019-12-07 09:09:26.1234567\r\n\
+        101,1,True,5,5,.\\Users\\Public,svchost.exe,.exe,51712,False,False,False,20.11.2021 01:02:03\r\n\
+        102,1,True,5,5,.,PsExec_IgnoreThisFile_ResearchTool.exe,.exe,834936,False,False,False,\r\n\
+        103,1,True,5,5,.\\Windows,System32,,0,True,False,False,2019-12-07 09:09:26.1234567\r\n\
+        104,1,False,5,5,.\\Windows\\System32,evil.exe,.exe,1024,False,False,False,2021-11-20 01:02:03.0000000\r\n\
+        105,1,True,5,5,.\\Windows\\System32,cmd.exe:Zone.Identifier,,26,False,False,True,\r\n\
+        106,1,True,5,5,.\\Windows,bad.exe,.exe,large,False,False,False,\r\n"
+    ).expect("Error writing MFTECmd csv.");
+
+    let results = MftECmdRecordIterator::from_path(&file_list)
+        .expect("Error opening MFTECmd csv.")
+        .collect::<Vec<_>>();
+    assert_eq!(results.len(), 7);
+    let error = results[6].as_ref().expect_err("Malformed FileSize should error.");
+    assert!(error.starts_with("row 7:"), "{}", error);
+
+    let records = results.into_iter()
+        .filter_map(|r| r.ok())
+        .filter(|r| r.is_file())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 3);
+    assert_eq!(records[0].full_name(), r"Windows\System32\cmd.exe");
+    assert_eq!(records[2].full_name(), "PsExec_IgnoreThisFile_ResearchTool.exe");
+    assert!(records[0].created.is_some());
+    // Timestamps in other formats do not fail the row
+    assert_eq!(records[1].created, None);
+
+    let reader = get_sample_reader();
+    let mut triage = Triage::new(&reader, LookupFilter::default());
+    let verdicts = records.into_iter()
+        .map(|r| {
+            triage.triage_record(&TriageRecord::from(r))
+                .expect("Error triaging record.")
+                .verdict
+                .verdict
+        })
+        .collect::<Vec<Verdict>>();
+    assert_eq!(verdicts, vec![Verdict::Vanilla, Verdict::MasqueradeCandidate, Verdict::Vanilla]);
+}
+
+
+/// Read all the records of a RecordSource
+fn read_source(source: &mut dyn RecordSource) -> Vec<Result<TriageRecord, String>> {
+    let mut records = Vec::new();
+    while let Some(result) = source.next_record() {
+        records.push(result);
+    }
+    records
+}
+
+
+#[test]
+fn test_jsonl_source() {
+    let velociraptor = concat!(
+        r#"{"OSPath":"C:\\Windows\\System32\\cmd.exe","Size":289792,"IsDir":false,"Hash":{"MD5":"D760CBE8ADA11F064A08D60AF62FD9F2","SHA1":"0000000000000000000000000000000000000000","SHA256":""}}"#, "\n",
+        "\n",
+        r#"{"OSPath":"C:\\Users\\Public\\svchost.exe","Size":"51712"}"#, "\n",
+        r#"{"Size":1}"#, "\n",
+        "not json\n"
+    );
+    let mut source = JsonlRecordSource::new(velociraptor.as_bytes(), FieldMapping::velociraptor());
+    let results = read_source(&mut source);
+    assert_eq!(results.len(), 4);
+
+    let cmd = results[0].as_ref().expect("Error reading record.");
+    assert_eq!(cmd.directory, r"C:\Windows\System32");
+    assert_eq!(cmd.name, "cmd.exe");
+    assert_eq!(cmd.length, Some(289792));
+    assert_eq!(cmd.md5.as_deref(), Some("D760CBE8ADA11F064A08D60AF62FD9F2"));
+    assert_eq!(cmd.sha256, None);
+    assert_eq!(results[1].as_ref().map(|r| r.length), Ok(Some(51712)));
+    assert_eq!(results[2], Err("line 4: Missing value for OSPath".to_string()));
+    assert!(results[3].as_ref().expect_err("Malformed json should error.").starts_with("line 5:"));
+
+    let reader = get_sample_reader();
+    let verdict = Triage::new(&reader, LookupFilter::default())
+        .triage_record(cmd)
+        .expect("Error triaging record.");
+    assert_eq!(verdict.verdict.verdict, Verdict::HashMismatch);
+
+    // Custom mappings with dotted paths
+    let mut mapping = FieldMapping::from_preset("velociraptor").expect("Unknown preset.");
+    mapping.update("path=File.Path, hash=File.Hashes.Sha256").expect("Error updating mapping.");
+    let line = r#"{"File":{"Path":"/Windows/notepad.exe","Hashes":{"Sha256":"E066F5907F9EFDB760DA0377A7B5664C815D667FB2A7B370AA4A49783F4FEA0D"}}}"#;
+    let mut source = JsonlRecordSource::new(line.as_bytes(), mapping);
+    let record = source.next_record()
+        .expect("No record.")
+        .expect("Error reading record.");
+    assert_eq!(record.directory, "/Windows");
+    assert_eq!(record.name, "notepad.exe");
+    assert!(record.sha256.is_some());
+    assert!(source.next_record().is_none());
+
+    assert!("size=Size,owner=Owner".parse::<FieldMapping>().is_err());
+    assert!(FieldMapping::from_preset("unknown").is_err());
+}
+
+
+#[test]
+fn test_kape_records() {
+    // The json output of the MFTECmd $MFT module of KAPE
+    let kape = concat!(
+        r#"{"EntryNumber":35121,"ParentPath":".\\Windows","FileName":"System32","IsDirectory":true,"FileSize":0}"#, "\n",
+        r#"{"EntryNumber":48812,"ParentPath":".\\Windows\\System32","FileName":"cmd.exe","Extension":".exe","IsDirectory":false,"FileSize":289792}"#, "\n",
+        r#"{"EntryNumber":5,"ParentPath":".","FileName":"bootmgr","IsDirectory":false,"FileSize":413738}"#, "\n",
+        r#"{"EntryNumber":6,"ParentPath":".\\Windows","IsDirectory":false,"FileSize":0}"#, "\n"
+    );
+    let mapping = FieldMapping::from_preset("kape").expect("Unknown preset.");
+    let mut source = JsonlRecordSource::new(kape.as_bytes(), mapping);
+    let results = read_source(&mut source);
+
+    // The directory is skipped
+    assert_eq!(results.len(), 3);
+    let cmd = results[0].clone().expect("Error reading record.");
+    assert_eq!(cmd.full_name, r".\Windows\System32\cmd.exe");
+    assert_eq!(cmd.directory, r".\Windows\System32");
+    assert_eq!(cmd.name, "cmd.exe");
+    assert_eq!(cmd.length, Some(289792));
+    assert_eq!(cmd.get_hash(), None);
+    assert_eq!(results[1].as_ref().map(|r| r.full_name.as_str()), Ok(r".\bootmgr"));
+    assert_eq!(results[2], Err("line 4: Missing value for FileName".to_string()));
+
+    let reader = get_sample_reader();
+    let verdict = Triage::new(&reader, LookupFilter::default())
+        .triage_record(&cmd)
+        .expect("Error triaging record.");
+    assert_eq!(verdict.verdict.verdict, Verdict::Vanilla);
+}
+
+
+#[test]
+fn test_record_sources() {
+    let file_list = WindowsFileList::from_folder("samples/W10_20H2_Pro_19042.631")
+        .expect("Error creating WindowsFileList.");
+
+    let mut csv_source = file_list.into_iter()
+        .expect("Error creating WinFileListRecordIterator.");
+    let from_values = read_source(&mut csv_source);
+
+    let mut typed_source = CsvFileRecordIterator::from_path(file_list.file_list_path())
+        .expect("Error creating CsvFileRecordIterator.");
+    let from_typed = read_source(&mut typed_source);
+
+    assert_eq!(from_values.len(), 16);
+    assert_eq!(from_values, from_typed);
+}
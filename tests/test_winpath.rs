@@ -0,0 +1,139 @@
+use winvanilla::winpath::{expand_environment_variables, get_redirected_directories, Volume, WindowsPath};
+
+
+#[test]
+fn test_windows_path_volumes() {
+    let cases: &[(&str, Volume)] = &[
+        (r"C:\Windows\System32\cmd.exe", Volume::Drive('C')),
+        ("c:/Windows/System32/cmd.exe", Volume::Drive('C')),
+        (r"\\?\C:\Windows\System32\cmd.exe", Volume::Drive('C')),
+        (r"\??\C:\Windows\System32\cmd.exe", Volume::Drive('C')),
+        (r"\Device\HarddiskVolume3\Windows\System32\cmd.exe", Volume::HarddiskVolume(3)),
+        (r"\\?\GLOBALROOT\Device\HarddiskVolume3\Windows\System32\cmd.exe", Volume::HarddiskVolume(3)),
+        (
+            r"\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy12\Windows\System32\cmd.exe",
+            Volume::ShadowCopy(12)
+        ),
+        (
+            r"\\WKS01\c$\Windows\System32\cmd.exe",
+            Volume::AdminShare { host: "WKS01".to_string(), drive: 'C' }
+        ),
+        (
+            r"\\?\UNC\WKS01\C$\Windows\System32\cmd.exe",
+            Volume::AdminShare { host: "WKS01".to_string(), drive: 'C' }
+        ),
+        (r"\Windows\System32\cmd.exe", Volume::None),
+        (r"Windows\System32\cmd.exe", Volume::None),
+    ];
+
+    for (path, volume) in cases {
+        let windows_path = WindowsPath::new(path);
+        assert_eq!(windows_path.volume(), volume, "{}", path);
+        assert_eq!(windows_path.relative_path(), r"Windows\System32\cmd.exe", "{}", path);
+        assert_eq!(windows_path.file_name(), "cmd.exe", "{}", path);
+        assert_eq!(windows_path.parent(), r"Windows\System32", "{}", path);
+    }
+}
+
+
+#[test]
+fn test_windows_path_normalization() {
+    let path = WindowsPath::new(r"C:\Windows\\System32\.\drivers\..\cmd.exe");
+    assert_eq!(path.relative_path(), r"Windows\System32\cmd.exe");
+    assert_eq!(path.to_string(), r"C:\Windows\System32\cmd.exe");
+
+    let path = WindowsPath::new(r"C:\");
+    assert_eq!(path.volume(), &Volume::Drive('C'));
+    assert_eq!(path.relative_path(), "");
+    assert_eq!(path.file_name(), "");
+    assert_eq!(path.parent(), "");
+
+    // Only the line ending and quotes of the input are removed
+    let path = WindowsPath::new("\"C:\\Windows\\explorer.exe \"\r\n");
+    assert_eq!(path.relative_path(), r"Windows\explorer.exe ");
+    assert_eq!(path.file_name(), "explorer.exe ");
+
+    assert_eq!(WindowsPath::new("c:/Windows/System32/").relative_path(), r"Windows\System32");
+    assert_eq!(WindowsPath::new(r"\Windows").relative_path(), "Windows");
+
+    let path = WindowsPath::new("pagefile.sys");
+    assert_eq!(path.file_name(), "pagefile.sys");
+    assert_eq!(path.parent(), "");
+
+    // Shares that are not admin shares are not volumes
+    let path = WindowsPath::new(r"\\fileserver\tools\cmd.exe");
+    assert_eq!(path.volume(), &Volume::None);
+    assert_eq!(path.relative_path(), r"fileserver\tools\cmd.exe");
+
+    assert_eq!(
+        WindowsPath::new(r"\Device\HarddiskVolumeShadowCopy1\Windows").to_string(),
+        r"\\?\GLOBALROOT\Device\HarddiskVolumeShadowCopy1\Windows"
+    );
+}
+
+
+#[test]
+fn test_environment_variables() {
+    assert_eq!(expand_environment_variables(r"%SystemRoot%\system32\svchost.exe"), r"C:\Windows\system32\svchost.exe");
+    assert_eq!(expand_environment_variables(r"%windir%\explorer.exe"), r"C:\Windows\explorer.exe");
+    assert_eq!(expand_environment_variables(r"%ProgramFiles(x86)%\Internet Explorer"), r"C:\Program Files (x86)\Internet Explorer");
+    assert_eq!(expand_environment_variables(r"\SystemRoot\System32\drivers\ntfs.sys"), r"C:\Windows\System32\drivers\ntfs.sys");
+    assert_eq!(expand_environment_variables(r"%LOCALAPPDATA%\Temp"), r"C:\Users\%USERNAME%\AppData\Local\Temp");
+    // Unknown variables are left as they are
+    assert_eq!(expand_environment_variables(r"%MyTools%\tool.exe"), r"%MyTools%\tool.exe");
+
+    let path = WindowsPath::new(r"%SystemRoot%\system32\svchost.exe");
+    assert_eq!(path.volume(), &Volume::Drive('C'));
+    assert_eq!(path.relative_path(), r"Windows\system32\svchost.exe");
+}
+
+
+#[test]
+fn test_user_templates() {
+    let path = WindowsPath::new(r"C:\Users\jdoe\AppData\Local\Microsoft\OneDrive\OneDrive.exe");
+    assert_eq!(path.relative_path(), r"Users\%USERNAME%\AppData\Local\Microsoft\OneDrive\OneDrive.exe");
+    assert_eq!(path.user(), Some("jdoe"));
+    assert_eq!(path.to_string(), r"C:\Users\jdoe\AppData\Local\Microsoft\OneDrive\OneDrive.exe");
+
+    let path = WindowsPath::new(r"%APPDATA%\Microsoft\Windows\Start Menu");
+    assert_eq!(path.relative_path(), r"Users\%USERNAME%\AppData\Roaming\Microsoft\Windows\Start Menu");
+    assert_eq!(path.user(), None);
+
+    // The last component of a directory can be a profile, but not of a file
+    assert_eq!(WindowsPath::from_directory(r"C:\Users\jdoe").relative_path(), r"Users\%USERNAME%");
+    assert_eq!(WindowsPath::new(r"C:\Users\desktop.ini").relative_path(), r"Users\desktop.ini");
+
+    // Shared profiles and globs are not templated
+    assert_eq!(WindowsPath::new(r"C:\Users\Public\svchost.exe").relative_path(), r"Users\Public\svchost.exe");
+    assert_eq!(WindowsPath::from_directory(r"C:\Users\Default").relative_path(), r"Users\Default");
+    assert_eq!(WindowsPath::new(r"C:\Users\*\NTUSER.DAT").relative_path(), r"Users\*\NTUSER.DAT");
+}
+
+
+#[test]
+fn test_redirected_directories() {
+    assert_eq!(
+        get_redirected_directories(r"windows\syswow64\drivers"),
+        vec![
+            r"windows\syswow64\drivers", r"Windows\System32\drivers",
+            r"Windows\SysArm32\drivers", r"Windows\SyChpe32\drivers"
+        ]
+    );
+    assert_eq!(get_redirected_directories(r"Windows\Sysnative")[0], r"Windows\System32");
+    assert_eq!(
+        get_redirected_directories(r"Program Files (x86)\Internet Explorer"),
+        vec![
+            r"Program Files (x86)\Internet Explorer", r"Program Files\Internet Explorer",
+            r"Program Files (Arm)\Internet Explorer"
+        ]
+    );
+    // Only whole components are redirected
+    assert_eq!(get_redirected_directories(r"Windows\System32x"), vec![r"Windows\System32x"]);
+    assert_eq!(get_redirected_directories(r"Windows"), vec![r"Windows"]);
+    // Directories WOW64 does not redirect
+    assert_eq!(get_redirected_directories(r"Windows\System32\drivers\etc"), vec![r"Windows\System32\drivers\etc"]);
+    assert_eq!(get_redirected_directories(r"windows\system32\spool\drivers"), vec![r"windows\system32\spool\drivers"]);
+    assert_eq!(get_redirected_directories(r"Windows\Sysnative\catroot2"), vec![r"Windows\System32\catroot2"]);
+    assert_eq!(get_redirected_directories(r"Windows\SysWOW64\LogFiles"), vec![r"Windows\SysWOW64\LogFiles"]);
+    assert_eq!(get_redirected_directories(r"Windows\System32\catroot3").len(), 4);
+}